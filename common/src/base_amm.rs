@@ -3,6 +3,21 @@ use jupiter_amm_interface::AccountMap;
 
 use solana_program::pubkey::Pubkey;
 
+use crate::{PoolParam, PoolParamValue};
+
+/// Why a pool can or cannot currently be quoted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PoolReadiness {
+    Ready,
+
+    /// The pool has not been updated for the current epoch yet,
+    /// usually for a short while after an epoch rollover
+    AwaitingEpochUpdate,
+
+    /// Auxiliary accounts (e.g. validator list, reserves) have not been fetched yet
+    MissingAuxAccounts,
+}
+
 pub trait BaseStakePoolAmm {
     /// stake pool program ID
     /// NB: this is not necessarily the program to invoke to execute the deposit/withdraw:
@@ -21,4 +36,14 @@ pub trait BaseStakePoolAmm {
     fn get_accounts_to_update(&self) -> Vec<Pubkey>;
 
     fn update(&mut self, account_map: &AccountMap) -> Result<()>;
+
+    fn readiness(&self) -> PoolReadiness {
+        PoolReadiness::Ready
+    }
+
+    /// Current values of the pool's parameters whose changes are reported on update,
+    /// see [`crate::diff_pool_params()`]
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        Vec::new()
+    }
 }
@@ -6,7 +6,9 @@ use rust_decimal::{
 };
 use solana_program::instruction::Instruction;
 
-use crate::{BaseStakePoolAmm, DepositSolQuoteError};
+use crate::{
+    reverse_quote, BaseStakePoolAmm, DepositSolQuoteError, ExactOutUnreachableErr, StakedexSdkError,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct DepositSolQuote {
@@ -23,14 +25,34 @@ pub trait DepositSol: BaseStakePoolAmm {
     fn can_accept_sol_deposits(&self) -> bool;
 
     /// This should only include the stake pool's fees, not stakedex's global fees
-    fn get_deposit_sol_quote_unchecked(&self, lamports: u64) -> Result<DepositSolQuote>;
+    fn get_deposit_sol_quote_unchecked(
+        &self,
+        lamports: u64,
+    ) -> Result<DepositSolQuote, StakedexSdkError>;
 
     fn virtual_ix(&self) -> Result<Instruction>;
 
-    fn get_deposit_sol_quote(&self, lamports: u64) -> Result<DepositSolQuote> {
+    fn get_deposit_sol_quote(&self, lamports: u64) -> Result<DepositSolQuote, StakedexSdkError> {
+        if !self.can_accept_sol_deposits() {
+            return Err(DepositSolQuoteError::CannotAcceptSolDeposits.into());
+        }
+        self.get_deposit_sol_quote_unchecked(lamports)
+    }
+
+    /// Returns the quote with the minimum `in_amount` whose `out_amount` is at least `out_amount`
+    fn get_deposit_sol_quote_exact_out(
+        &self,
+        out_amount: u64,
+    ) -> Result<DepositSolQuote, StakedexSdkError> {
         if !self.can_accept_sol_deposits() {
             return Err(DepositSolQuoteError::CannotAcceptSolDeposits.into());
         }
+        let lamports = reverse_quote(out_amount, out_amount, |lamports| {
+            self.get_deposit_sol_quote_unchecked(lamports)
+                .ok()
+                .map(|q| q.out_amount)
+        })
+        .ok_or(ExactOutUnreachableErr)?;
         self.get_deposit_sol_quote_unchecked(lamports)
     }
 
@@ -7,7 +7,8 @@ use rust_decimal::{
 use solana_program::{instruction::Instruction, pubkey::Pubkey};
 
 use crate::{
-    apply_deposit_stake_stakedex_fee, wsol, AfterFees, BaseStakePoolAmm, DepositStakeQuoteErr,
+    apply_deposit_stake_stakedex_fee, reverse_quote, wsol, AfterFees, BaseStakePoolAmm,
+    DepositStakeQuoteErr, ExactOutUnreachableErr, StakedexSdkError,
 };
 
 use super::withdraw_stake::WithdrawStakeQuote;
@@ -58,6 +59,21 @@ pub trait DepositStake: BaseStakePoolAmm {
         Ok(self.get_deposit_stake_quote_unchecked(withdraw_stake_quote))
     }
 
+    /// Finds the smallest stake account delegated to `voter` whose deposit yields at least
+    /// `out_amount` after stakedex's global fees ([`Self::convert_deposit_stake_quote()`]).
+    ///
+    /// Returns (stake account to be deposited, deposit stake quote for it)
+    fn get_deposit_stake_quote_exact_out(
+        &self,
+        out_amount: u64,
+        voter: Pubkey,
+    ) -> Result<(WithdrawStakeQuote, DepositStakeQuote), StakedexSdkError> {
+        if !self.can_accept_stake_deposits() {
+            return Err(DepositStakeQuoteErr::CannotAcceptStakeDeposits.into());
+        }
+        reverse_deposit_stake_quote(self, out_amount, voter, out_amount)
+    }
+
     fn can_accept_stake_deposits(&self) -> bool;
 
     /// Inner impl fn, should not be called directly. Instead, call
@@ -106,3 +122,33 @@ pub trait DepositStake: BaseStakePoolAmm {
 
     fn accounts_len(&self) -> usize;
 }
+
+/// Reverse quotes a deposit stake operation by searching over stake account lamports,
+/// starting from `initial_lamports`.
+///
+/// Does not check [`DepositStake::can_accept_stake_deposits()`].
+pub fn reverse_deposit_stake_quote<D: DepositStake + ?Sized>(
+    deposit_to: &D,
+    out_amount: u64,
+    voter: Pubkey,
+    initial_lamports: u64,
+) -> Result<(WithdrawStakeQuote, DepositStakeQuote), StakedexSdkError> {
+    let quote_for_lamports = |lamports: u64| {
+        let wsq = WithdrawStakeQuote::from_lamports_and_voter(lamports, voter);
+        let dsq = deposit_to.get_deposit_stake_quote_unchecked(wsq);
+        (wsq, dsq)
+    };
+    let lamports = reverse_quote(out_amount, initial_lamports, |lamports| {
+        let (_wsq, dsq) = quote_for_lamports(lamports);
+        if dsq.is_zero_out() {
+            return None;
+        }
+        Some(
+            deposit_to
+                .convert_deposit_stake_quote(lamports, dsq)
+                .out_amount,
+        )
+    })
+    .ok_or(ExactOutUnreachableErr)?;
+    Ok(quote_for_lamports(lamports))
+}
@@ -22,6 +22,26 @@ pub enum DepositSolQuoteError {
     CannotAcceptSolDeposits,
 }
 
+#[derive(thiserror::Error, Copy, Clone, Debug, PartialEq)]
+#[error("No input amount found that yields the requested output amount")]
+pub struct ExactOutUnreachableErr;
+
+/// Errors when building instructions from a previously computed quote
+#[derive(thiserror::Error, Copy, Clone, Debug, PartialEq)]
+pub enum StaleQuoteErr {
+    #[error("Quote was computed in a different epoch")]
+    EpochChanged,
+
+    #[error("Swap amount does not match quoted amount")]
+    AmountMismatch,
+
+    #[error("Prefund repay params changed since quote")]
+    PrefundChanged,
+
+    #[error("Stake pool can no longer service the quoted route")]
+    RouteInvalidated,
+}
+
 #[derive(thiserror::Error, Copy, Clone, Debug, PartialEq)]
 pub enum SwapViaStakeQuoteErr {
     #[error("{0}")]
@@ -45,3 +65,143 @@ impl From<DepositStakeQuoteErr> for SwapViaStakeQuoteErr {
         Self::Deposit(value)
     }
 }
+
+/// Error type of the stakedex_sdk public API and the pool traits' quote methods
+#[derive(thiserror::Error, Debug)]
+pub enum StakedexSdkError {
+    #[error("No stake pool found for mint {0}")]
+    UnknownMint(Pubkey),
+
+    #[error("Stake pool {0} is quarantined until it updates successfully")]
+    PoolQuarantined(Pubkey),
+
+    #[error("Stake pool has not been updated for this epoch")]
+    PoolNotUpdatedThisEpoch,
+
+    #[error("Deposit will exceed the stake pool's deposit cap")]
+    DepositCapExceeded,
+
+    #[error("Stake pool has insufficient reserve liquidity")]
+    InsufficientReserveLiquidity,
+
+    #[error("Stake pool does not accept stake delegated to this validator")]
+    ValidatorNotAccepted,
+
+    #[error("Math overflow")]
+    MathOverflow,
+
+    #[error("Amount too small")]
+    AmountTooSmall,
+
+    #[error("Withdrawal would leave the stake pool's validators imbalanced")]
+    WithdrawalLeavesPoolImbalanced,
+
+    #[error("Withdrawal would leave the stake account below its minimum balance")]
+    WithdrawalBelowMinStakeAccountBalance,
+
+    #[error("Stake pool has no LST supply")]
+    NoLstSupply,
+
+    #[error("{0}")]
+    CannotAcceptSolDeposits(DepositSolQuoteError),
+
+    #[error("Stake pool cannot accept SOL withdrawals at this time")]
+    CannotAcceptSolWithdrawals,
+
+    #[error("{0}")]
+    CannotAcceptStakeDeposits(DepositStakeQuoteErr),
+
+    #[error("{0}")]
+    CannotAcceptStakeWithdrawals(WithdrawStakeQuoteErr),
+
+    #[error("No route found between pools")]
+    NoRouteFound,
+
+    #[error("No unused bridge stake seed found")]
+    NoFreeBridgeStakeSeed,
+
+    #[error("{0}")]
+    ExactOutUnreachable(ExactOutUnreachableErr),
+
+    #[error("{0}")]
+    StaleQuote(StaleQuoteErr),
+
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<DepositSolQuoteError> for StakedexSdkError {
+    fn from(value: DepositSolQuoteError) -> Self {
+        Self::CannotAcceptSolDeposits(value)
+    }
+}
+
+impl From<DepositStakeQuoteErr> for StakedexSdkError {
+    fn from(value: DepositStakeQuoteErr) -> Self {
+        Self::CannotAcceptStakeDeposits(value)
+    }
+}
+
+impl From<WithdrawStakeQuoteErr> for StakedexSdkError {
+    fn from(value: WithdrawStakeQuoteErr) -> Self {
+        Self::CannotAcceptStakeWithdrawals(value)
+    }
+}
+
+impl From<SwapViaStakeQuoteErr> for StakedexSdkError {
+    fn from(value: SwapViaStakeQuoteErr) -> Self {
+        match value {
+            SwapViaStakeQuoteErr::Deposit(e) => e.into(),
+            SwapViaStakeQuoteErr::Withdraw(e) => e.into(),
+            SwapViaStakeQuoteErr::NoRouteFound => Self::NoRouteFound,
+        }
+    }
+}
+
+impl From<ExactOutUnreachableErr> for StakedexSdkError {
+    fn from(value: ExactOutUnreachableErr) -> Self {
+        Self::ExactOutUnreachable(value)
+    }
+}
+
+impl From<StaleQuoteErr> for StakedexSdkError {
+    fn from(value: StaleQuoteErr) -> Self {
+        Self::StaleQuote(value)
+    }
+}
+
+impl From<std::io::Error> for StakedexSdkError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Other(value.into())
+    }
+}
+
+/// Recovers the error kind if `value` wraps one of this crate's error types,
+/// otherwise falls back to [`StakedexSdkError::Other`]
+impl From<anyhow::Error> for StakedexSdkError {
+    fn from(value: anyhow::Error) -> Self {
+        let value = match value.downcast::<Self>() {
+            Ok(e) => return e,
+            Err(value) => value,
+        };
+        if let Some(e) = value.downcast_ref::<SwapViaStakeQuoteErr>() {
+            return (*e).into();
+        }
+        if let Some(e) = value.downcast_ref::<DepositStakeQuoteErr>() {
+            return (*e).into();
+        }
+        if let Some(e) = value.downcast_ref::<WithdrawStakeQuoteErr>() {
+            return (*e).into();
+        }
+        if let Some(e) = value.downcast_ref::<DepositSolQuoteError>() {
+            return (*e).into();
+        }
+        if let Some(e) = value.downcast_ref::<ExactOutUnreachableErr>() {
+            return (*e).into();
+        }
+        if let Some(e) = value.downcast_ref::<StaleQuoteErr>() {
+            return (*e).into();
+        }
+        Self::Other(value)
+    }
+}
@@ -7,6 +7,10 @@ mod errs;
 mod fees;
 mod init_from_keyed_account;
 mod pda;
+mod pda_cache;
+mod pool_params;
+mod record_list;
+mod reverse_quote;
 mod withdraw_sol;
 mod withdraw_stake;
 
@@ -19,5 +23,9 @@ pub use errs::*;
 pub use fees::*;
 pub use init_from_keyed_account::*;
 pub use pda::*;
+pub use pda_cache::*;
+pub use pool_params::*;
+pub use record_list::*;
+pub use reverse_quote::*;
 pub use withdraw_sol::*;
 pub use withdraw_stake::*;
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+use solana_program::pubkey::Pubkey;
+
+use crate::find_fee_token_acc;
+
+/// Memoized PDAs keyed by whatever they are derived from besides constant seeds,
+/// e.g. a validator's vote account, so that building instructions does not
+/// repeat [`Pubkey::find_program_address()`].
+///
+/// Immutable so that reads do not lock. [`Self::populate()`] replaces the map of
+/// the pool version being updated, while clones, e.g. of published snapshots,
+/// keep reading the map they were cloned with.
+pub struct PdaCache<K>(Arc<HashMap<K, Pubkey>>);
+
+impl<K> Clone for PdaCache<K> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K> Default for PdaCache<K> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<K> fmt::Debug for PdaCache<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PdaCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<K> PdaCache<K> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Eq + Hash> PdaCache<K> {
+    pub fn get(&self, key: &K) -> Option<Pubkey> {
+        self.0.get(key).copied()
+    }
+
+    /// The cached PDA of `key`, else the one found with `find` without caching it,
+    /// e.g. for a validator added since the last [`Self::populate()`]
+    pub fn get_or_find(&self, key: &K, find: impl FnOnce() -> Pubkey) -> Pubkey {
+        self.get(key).unwrap_or_else(find)
+    }
+
+    /// Caches the PDAs of exactly the keys of `items`, e.g. a pool's current validators on update.
+    ///
+    /// Does nothing if the keys are unchanged. Otherwise builds a new map,
+    /// only finding the PDAs of keys that are not cached yet.
+    pub fn populate<T>(
+        &mut self,
+        items: impl Iterator<Item = T> + Clone,
+        key: impl Fn(&T) -> K,
+        find: impl Fn(&T) -> Pubkey,
+    ) {
+        let mut len = 0;
+        let unchanged = items.clone().all(|item| {
+            len += 1;
+            self.0.contains_key(&key(&item))
+        }) && len == self.0.len();
+        if unchanged {
+            return;
+        }
+        let populated = items
+            .map(|item| {
+                let key = key(&item);
+                let pda = self.get(&key).unwrap_or_else(|| find(&item));
+                (key, pda)
+            })
+            .collect();
+        self.0 = Arc::new(populated);
+    }
+}
+
+/// Max number of mints whose fee token accounts [`fee_token_acc()`] caches
+pub const MAX_CACHED_FEE_TOKEN_ACCS: usize = 1024;
+
+/// [`find_fee_token_acc()`], cached for up to [`MAX_CACHED_FEE_TOKEN_ACCS`] mints
+/// for the lifetime of the process. Mints beyond that are found on every call.
+pub fn fee_token_acc(mint: &Pubkey) -> Pubkey {
+    static CACHE: OnceLock<RwLock<HashMap<Pubkey, Pubkey>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(pda) = cache
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(mint)
+    {
+        return *pda;
+    }
+    let pda = find_fee_token_acc(mint).0;
+    let mut cache = cache.write().unwrap_or_else(PoisonError::into_inner);
+    if cache.len() < MAX_CACHED_FEE_TOKEN_ACCS {
+        cache.insert(*mint, pda);
+    }
+    pda
+}
@@ -0,0 +1,102 @@
+use solana_program::pubkey::Pubkey;
+
+/// A pool parameter that rarely changes but affects quoting or routing,
+/// see [`crate::BaseStakePoolAmm::params()`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PoolParam {
+    StakeDepositFee,
+    SolDepositFee,
+    StakeWithdrawalFee,
+    SolWithdrawalFee,
+    StakeReferralFee,
+    SolReferralFee,
+
+    /// Fee charged on staking rewards, e.g. SPL's epoch fee or Marinade's reward fee
+    RewardFee,
+
+    StakeDepositAuthority,
+    SolDepositAuthority,
+    SolWithdrawAuthority,
+    PreferredDepositValidator,
+    PreferredWithdrawValidator,
+
+    /// SPL deposit cap guard's cap or Marinade's staking SOL cap
+    DepositCap,
+
+    Paused,
+
+    /// Whether stake can be withdrawn, e.g. Marinade's withdraw_stake_account_enabled
+    WithdrawStakeEnabled,
+
+    /// unstake.it's fee on DepositStake
+    UnstakeFee,
+
+    /// unstake.it's share of [`Self::UnstakeFee`]
+    ProtocolFee,
+
+    /// Referrer's share of [`Self::ProtocolFee`]
+    ReferrerFee,
+
+    ProtocolFeeDestination,
+}
+
+/// `num / denom`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FeeRatio {
+    pub num: u64,
+    pub denom: u64,
+}
+
+/// The value of a [`PoolParam`], as stored on-chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PoolParamValue {
+    /// Unset, e.g. no preferred validator or no deposit cap
+    None,
+
+    Bool(bool),
+
+    Pubkey(Pubkey),
+
+    Ratio(FeeRatio),
+
+    /// Linearly interpolated between the ratios at max and zero liquidity remaining
+    LiquidityLinear {
+        max_liq_remaining: FeeRatio,
+        zero_liq_remaining: FeeRatio,
+    },
+
+    Lamports(u64),
+
+    /// Amount of the pool's LST, in atomics
+    LstAtomics(u64),
+}
+
+impl From<Option<Pubkey>> for PoolParamValue {
+    fn from(pubkey: Option<Pubkey>) -> Self {
+        pubkey.map_or(Self::None, Self::Pubkey)
+    }
+}
+
+/// The [`PoolParam`]s whose values differ between `old` and `new` params of the same pool,
+/// as `(param, old_value, new_value)` in `new`'s order.
+///
+/// Params missing from either side are treated as [`PoolParamValue::None`].
+pub fn diff_pool_params<'a>(
+    old: &'a [(PoolParam, PoolParamValue)],
+    new: &'a [(PoolParam, PoolParamValue)],
+) -> impl Iterator<Item = (PoolParam, PoolParamValue, PoolParamValue)> + 'a {
+    let value_in = |params: &[(PoolParam, PoolParamValue)], param: PoolParam| {
+        params
+            .iter()
+            .find(|(p, _)| *p == param)
+            .map_or(PoolParamValue::None, |(_, v)| *v)
+    };
+    let removed = old
+        .iter()
+        .filter(move |(param, _)| new.iter().all(|(p, _)| p != param))
+        .map(|(param, old_value)| (*param, *old_value, PoolParamValue::None));
+    new.iter()
+        .map(move |(param, new_value)| (*param, value_in(old, *param), *new_value))
+        .chain(removed)
+        .filter(|(_, old_value, new_value)| old_value != new_value)
+}
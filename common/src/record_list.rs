@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+/// The raw bytes of a list of fixed-size records, e.g. a stake pool's validator list,
+/// so that updating it is a memcpy and records are only parsed when read.
+///
+/// Excludes the account's header and unused capacity.
+#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct RecordList {
+    data: Vec<u8>,
+    record_len: u32,
+}
+
+impl RecordList {
+    /// Copies the first `len` records of `record_len` bytes each from `records`,
+    /// reusing the existing allocation
+    pub fn update(&mut self, records: &[u8], record_len: usize, len: usize) -> Result<()> {
+        let data = len
+            .checked_mul(record_len)
+            .and_then(|n| records.get(..n))
+            .ok_or_else(|| anyhow!("Expected {} records of {} bytes", len, record_len))?;
+        self.record_len = record_len.try_into()?;
+        self.data.clear();
+        self.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// All records, back to back
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn record_len(&self) -> usize {
+        self.record_len as usize
+    }
+
+    pub fn len(&self) -> usize {
+        match self.record_len() {
+            0 => 0,
+            record_len => self.data.len() / record_len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let start = index.checked_mul(self.record_len())?;
+        self.data.get(start..start.checked_add(self.record_len())?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + Clone {
+        // chunks_exact() panics on 0
+        self.data.chunks_exact(self.record_len().max(1))
+    }
+
+    /// Index of the first record with `key` at byte `offset`, e.g. a validator's vote account,
+    /// without parsing any records
+    pub fn position_by_pubkey(&self, offset: usize, key: &Pubkey) -> Option<usize> {
+        let end = offset.checked_add(32)?;
+        self.iter()
+            .position(|record| record.get(offset..end) == Some(key.as_ref()))
+    }
+}
@@ -0,0 +1,69 @@
+/// Max number of times the input amount is scaled up before giving up
+const MAX_SCALE_UP_ITERS: usize = 64;
+
+/// Finds the minimum input amount whose quoted output is >= `target_out`.
+///
+/// `quote_out` should return the output amount for a given input amount, or `None` if
+/// the input amount cannot be quoted. It is assumed to be monotonically non-decreasing
+/// where it returns `Some`.
+///
+/// `initial_in` is the first input amount tried. A good estimate (e.g. from an approximate
+/// reverse fee calculation) reduces the number of `quote_out` calls required.
+///
+/// Returns `None` if no input amount that yields at least `target_out` was found.
+pub fn reverse_quote<F: Fn(u64) -> Option<u64>>(
+    target_out: u64,
+    initial_in: u64,
+    quote_out: F,
+) -> Option<u64> {
+    let meets_target = |amt: u64| quote_out(amt).is_some_and(|out| out >= target_out);
+
+    // 1. scale input up until it meets the target
+    let mut hi = initial_in.max(1);
+    let mut iters = 0;
+    loop {
+        let out = quote_out(hi);
+        if out.is_some_and(|out| out >= target_out) {
+            break;
+        }
+        iters += 1;
+        if iters >= MAX_SCALE_UP_ITERS {
+            return None;
+        }
+        // secant step assuming output is roughly proportional to input,
+        // falling back to doubling if output is 0 or not quotable
+        let scaled = match out {
+            Some(out) if out > 0 => {
+                u64::try_from((hi as u128 * target_out as u128).div_ceil(out as u128)).ok()
+            }
+            _ => None,
+        };
+        hi = scaled.filter(|s| *s > hi).or_else(|| hi.checked_mul(2))?;
+    }
+
+    // 2. gallop down to find a lower bound that does not meet the target
+    let mut step: u64 = 1;
+    let lo = loop {
+        let candidate = hi.saturating_sub(step);
+        if candidate == 0 || !meets_target(candidate) {
+            break candidate;
+        }
+        hi = candidate;
+        step = step.saturating_mul(2);
+    };
+    if lo == 0 && meets_target(0) {
+        return Some(0);
+    }
+
+    // 3. binary search for min amount in (lo, hi] that meets the target
+    let (mut lo, mut hi) = (lo, hi);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if meets_target(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Some(hi)
+}
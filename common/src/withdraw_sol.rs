@@ -6,7 +6,10 @@ use rust_decimal::{
 };
 use solana_program::{instruction::Instruction, pubkey::Pubkey};
 
-use crate::{apply_withdraw_wrapped_sol_stakedex_fee, wsol, BaseStakePoolAmm};
+use crate::{
+    apply_withdraw_wrapped_sol_stakedex_fee, reverse_quote, wsol, BaseStakePoolAmm,
+    ExactOutUnreachableErr, StakedexSdkError,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct WithdrawSolQuote {
@@ -21,7 +24,22 @@ pub struct WithdrawSolQuote {
 
 pub trait WithdrawSol: BaseStakePoolAmm {
     /// This should only include the stake pool's fees, not stakedex's global fees
-    fn get_withdraw_sol_quote(&self, lst: u64) -> Result<WithdrawSolQuote>;
+    fn get_withdraw_sol_quote(&self, lst: u64) -> Result<WithdrawSolQuote, StakedexSdkError>;
+
+    /// Returns the quote with the minimum `in_amount` whose output,
+    /// after stakedex's global fees ([`Self::convert_quote()`]), is at least `out_amount`
+    fn get_withdraw_sol_quote_exact_out(
+        &self,
+        out_amount: u64,
+    ) -> Result<WithdrawSolQuote, StakedexSdkError> {
+        let lst = reverse_quote(out_amount, out_amount, |lst| {
+            self.get_withdraw_sol_quote(lst)
+                .ok()
+                .map(|q| apply_withdraw_wrapped_sol_stakedex_fee(q.out_amount).remainder)
+        })
+        .ok_or(ExactOutUnreachableErr)?;
+        self.get_withdraw_sol_quote(lst)
+    }
 
     fn virtual_ix(&self) -> Result<Instruction>;
 
@@ -2,6 +2,8 @@
 // remove once everyone has upgraded to ^1.17
 #![allow(deprecated)]
 
+use std::ops::ControlFlow;
+
 use anyhow::{anyhow, Result};
 use solana_program::{
     clock::Clock,
@@ -109,10 +111,22 @@ pub trait WithdrawStakeBase {
 }
 
 pub trait WithdrawStake: BaseStakePoolAmm + WithdrawStakeBase {
+    /// Boxes the iterator, prefer [`Self::try_for_each_withdraw_stake_quote()`]
+    /// in hot paths since this heap-allocates on every call
     fn withdraw_stake_quote_iter_dyn(
         &self,
         withdraw_amount: u64,
     ) -> Box<dyn Iterator<Item = WithdrawStakeQuote> + '_>;
+
+    /// Calls `f` with each item of [`WithdrawStakeIter::withdraw_stake_quote_iter()`]
+    /// until it returns [`ControlFlow::Break`].
+    ///
+    /// Object-safe without heap-allocating, unlike [`Self::withdraw_stake_quote_iter_dyn()`]
+    fn try_for_each_withdraw_stake_quote(
+        &self,
+        withdraw_amount: u64,
+        f: &mut dyn FnMut(WithdrawStakeQuote) -> ControlFlow<()>,
+    );
 }
 
 impl<T: WithdrawStakeIter + WithdrawStakeBase + BaseStakePoolAmm> WithdrawStake for T {
@@ -122,4 +136,15 @@ impl<T: WithdrawStakeIter + WithdrawStakeBase + BaseStakePoolAmm> WithdrawStake
     ) -> Box<dyn Iterator<Item = WithdrawStakeQuote> + '_> {
         Box::new(self.withdraw_stake_quote_iter(withdraw_amount))
     }
+
+    fn try_for_each_withdraw_stake_quote(
+        &self,
+        withdraw_amount: u64,
+        f: &mut dyn FnMut(WithdrawStakeQuote) -> ControlFlow<()>,
+    ) {
+        // ignore the result: the caller already knows whether it broke
+        let _ = self
+            .withdraw_stake_quote_iter(withdraw_amount)
+            .try_for_each(f);
+    }
 }
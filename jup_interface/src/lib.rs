@@ -1,6 +1,8 @@
 pub mod jupiter_stakedex_interface;
 mod pool_pair;
 mod pool_sol;
+mod shared_pool;
 
 pub use pool_pair::*;
 pub use pool_sol::*;
+pub use shared_pool::*;
@@ -0,0 +1,201 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::BuildHasher,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use rand::{rngs::ThreadRng, Rng, RngCore};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use stakedex_sdk_common::{find_bridge_stake, slumdog_stake_create_with_seed, StakedexSdkError};
+
+/// Default number of random seeds tried by [`BridgeSeedAllocator::allocate()`] before giving up
+pub const DEFAULT_MAX_BRIDGE_SEED_ATTEMPTS: usize = 16;
+
+/// Answers whether an account currently exists on-chain.
+///
+/// Implemented for fetched account maps and pubkey sets so that
+/// a local stand-in can be used in place of an RPC.
+pub trait AccountExistence {
+    fn account_exists(&self, pubkey: &Pubkey) -> bool;
+}
+
+impl<S: BuildHasher> AccountExistence for HashMap<Pubkey, Account, S> {
+    fn account_exists(&self, pubkey: &Pubkey) -> bool {
+        self.contains_key(pubkey)
+    }
+}
+
+impl<S: BuildHasher> AccountExistence for HashSet<Pubkey, S> {
+    fn account_exists(&self, pubkey: &Pubkey) -> bool {
+        self.contains(pubkey)
+    }
+}
+
+impl<T: AccountExistence + ?Sized> AccountExistence for &T {
+    fn account_exists(&self, pubkey: &Pubkey) -> bool {
+        (**self).account_exists(pubkey)
+    }
+}
+
+/// Picks the `bridge_stake_seed` of a pair Amm's swap for the swapping user,
+/// see [`crate::TwoWayPoolPair::with_bridge_seed_source()`].
+///
+/// Pair Amms without one pick a random seed without checking that it is free.
+pub type BridgeSeedSource = Arc<dyn Fn(&Pubkey) -> Result<u32, StakedexSdkError> + Send + Sync>;
+
+/// Picks `bridge_stake_seed`s whose bridge stake and slumdog stake accounts
+/// do not already exist, since (Prefund)SwapViaStake fails if either of them does.
+#[derive(Debug)]
+pub struct BridgeSeedAllocator<O, R = ThreadRng> {
+    oracle: O,
+    rng: R,
+    max_attempts: usize,
+}
+
+impl<O: AccountExistence> BridgeSeedAllocator<O> {
+    pub fn new(oracle: O) -> Self {
+        Self::with_rng(oracle, rand::thread_rng())
+    }
+}
+
+impl<O: AccountExistence, R: RngCore> BridgeSeedAllocator<O, R> {
+    /// Use a seeded `rng` for deterministic seeds
+    pub fn with_rng(oracle: O, rng: R) -> Self {
+        Self {
+            oracle,
+            rng,
+            max_attempts: DEFAULT_MAX_BRIDGE_SEED_ATTEMPTS,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns whether both the bridge stake and slumdog stake accounts
+    /// derived from `user` and `bridge_stake_seed` do not exist
+    pub fn is_seed_free(&self, user: &Pubkey, bridge_stake_seed: u32) -> bool {
+        let (bridge_stake, _bump) = find_bridge_stake(user, &bridge_stake_seed.to_le_bytes());
+        if self.oracle.account_exists(&bridge_stake) {
+            return false;
+        }
+        match slumdog_stake_create_with_seed(&bridge_stake) {
+            Ok(slumdog_stake) => !self.oracle.account_exists(&slumdog_stake),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns a random free `bridge_stake_seed` for `user`.
+    ///
+    /// Errs with [`StakedexSdkError::NoFreeBridgeStakeSeed`] if
+    /// no free seed was found within the max number of attempts.
+    pub fn allocate(&mut self, user: &Pubkey) -> Result<u32, StakedexSdkError> {
+        for _ in 0..self.max_attempts {
+            let seed = self.rng.gen();
+            if self.is_seed_free(user, seed) {
+                return Ok(seed);
+            }
+        }
+        Err(StakedexSdkError::NoFreeBridgeStakeSeed)
+    }
+}
+
+impl<O, R> BridgeSeedAllocator<O, R>
+where
+    O: AccountExistence + Send + 'static,
+    R: RngCore + Send + 'static,
+{
+    /// Shares this allocator between pair Amms and their clones.
+    ///
+    /// [`ThreadRng`] is not [`Send`], so use [`Self::with_rng()`] with e.g. a `StdRng`
+    pub fn into_source(self) -> BridgeSeedSource {
+        let allocator = Mutex::new(self);
+        Arc::new(move |user| {
+            allocator
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .allocate(user)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const RNG_SEED: u64 = 69;
+
+    fn first_seed() -> u32 {
+        StdRng::seed_from_u64(RNG_SEED).gen()
+    }
+
+    fn bridge_and_slumdog(user: &Pubkey, seed: u32) -> (Pubkey, Pubkey) {
+        let (bridge_stake, _bump) = find_bridge_stake(user, &seed.to_le_bytes());
+        (
+            bridge_stake,
+            slumdog_stake_create_with_seed(&bridge_stake).unwrap(),
+        )
+    }
+
+    #[test]
+    fn allocate_deterministic_with_seeded_rng() {
+        let user = Pubkey::new_unique();
+        let existing: HashSet<Pubkey> = HashSet::new();
+        let mut allocator =
+            BridgeSeedAllocator::with_rng(&existing, StdRng::seed_from_u64(RNG_SEED));
+        assert_eq!(allocator.allocate(&user).unwrap(), first_seed());
+    }
+
+    #[test]
+    fn allocate_skips_existing_bridge_stake() {
+        let user = Pubkey::new_unique();
+        let (bridge_stake, _) = bridge_and_slumdog(&user, first_seed());
+        let existing = HashSet::from([bridge_stake]);
+        let mut allocator =
+            BridgeSeedAllocator::with_rng(&existing, StdRng::seed_from_u64(RNG_SEED));
+        let seed = allocator.allocate(&user).unwrap();
+        assert_ne!(seed, first_seed());
+        assert!(allocator.is_seed_free(&user, seed));
+    }
+
+    #[test]
+    fn allocate_skips_existing_slumdog_stake() {
+        let user = Pubkey::new_unique();
+        let (_, slumdog_stake) = bridge_and_slumdog(&user, first_seed());
+        let existing = HashSet::from([slumdog_stake]);
+        let mut allocator =
+            BridgeSeedAllocator::with_rng(&existing, StdRng::seed_from_u64(RNG_SEED));
+        assert!(!allocator.is_seed_free(&user, first_seed()));
+        assert_ne!(allocator.allocate(&user).unwrap(), first_seed());
+    }
+
+    #[test]
+    fn source_allocates_free_seeds() {
+        let user = Pubkey::new_unique();
+        let (bridge_stake, _) = bridge_and_slumdog(&user, first_seed());
+        let existing = HashSet::from([bridge_stake]);
+        let source =
+            BridgeSeedAllocator::with_rng(existing.clone(), StdRng::seed_from_u64(RNG_SEED))
+                .into_source();
+        let seed = source(&user).unwrap();
+        assert_ne!(seed, first_seed());
+        assert!(BridgeSeedAllocator::new(&existing).is_seed_free(&user, seed));
+    }
+
+    #[test]
+    fn allocate_errs_when_exhausted() {
+        let user = Pubkey::new_unique();
+        let (bridge_stake, _) = bridge_and_slumdog(&user, first_seed());
+        let existing = HashSet::from([bridge_stake]);
+        let mut allocator =
+            BridgeSeedAllocator::with_rng(&existing, StdRng::seed_from_u64(RNG_SEED))
+                .with_max_attempts(1);
+        assert!(matches!(
+            allocator.allocate(&user),
+            Err(StakedexSdkError::NoFreeBridgeStakeSeed)
+        ));
+    }
+}
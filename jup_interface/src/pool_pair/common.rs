@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
-use jupiter_amm_interface::{Quote, QuoteParams, SwapParams};
+use arc_swap::ArcSwapOption;
+use jupiter_amm_interface::{Quote, QuoteParams, SwapMode, SwapParams};
 use rust_decimal::prelude::*;
 use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, stake, system_program, sysvar};
 use spl_token::native_mint;
@@ -9,14 +10,15 @@ use stakedex_interface::{
     PREFUND_WITHDRAW_STAKE_IX_ACCOUNTS_LEN,
 };
 use stakedex_sdk_common::{
-    apply_deposit_stake_stakedex_fee, find_bridge_stake, find_fee_token_acc,
+    apply_deposit_stake_stakedex_fee, fee_token_acc, find_bridge_stake, reverse_quote,
     slumdog_stake_create_with_seed, stakedex_program, unstake_it_pool, unstake_it_program, wsol,
-    AfterFees, DepositStake, DepositStakeInfo, DepositStakeQuote, SwapViaStakeQuoteErr,
-    WithdrawStake, WithdrawStakeQuote, WithdrawStakeQuoteErr, DEPOSIT_STAKE_DST_TOKEN_MINT_IDX,
+    AfterFees, DepositStake, DepositStakeInfo, DepositStakeQuote, ExactOutUnreachableErr,
+    StakedexSdkError, StaleQuoteErr, SwapViaStakeQuoteErr, WithdrawStake, WithdrawStakeQuote,
+    WithdrawStakeQuoteErr, DEPOSIT_STAKE_DST_TOKEN_MINT_IDX,
     PREFUND_WITHDRAW_STAKE_SRC_TOKEN_MINT_IDX, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
     SWAP_VIA_STAKE_DST_TOKEN_MINT_ACCOUNT_INDEX, SWAP_VIA_STAKE_SRC_TOKEN_MINT_ACCOUNT_INDEX,
 };
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::ControlFlow, sync::Arc};
 
 use crate::PrefundRepayParams;
 
@@ -29,6 +31,9 @@ use crate::PrefundRepayParams;
 ///    jup_v6_program_id as separator,
 ///    ...DepositStake metas
 /// ]
+///
+/// Recomputes the quote. Use [`manual_concat_get_account_metas_for_quote()`]
+/// to reuse a [`PoolPairQuote`] instead.
 pub fn manual_concat_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
     swap_params: &SwapParams,
     prefund_repay_params: &PrefundRepayParams,
@@ -36,7 +41,6 @@ pub fn manual_concat_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStak
     deposit_to: &D,
     bridge_stake_seed: u32,
 ) -> Result<Vec<AccountMeta>> {
-    // TODO: this is doing the same computation as it did in quote, should we cache this somehow?
     let prefund_split_lamports = prefund_repay_params.prefund_split_lamports()?;
     let (withdraw_quote, deposit_quote) = first_avail_prefund_quote(
         swap_params.in_amount,
@@ -44,6 +48,58 @@ pub fn manual_concat_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStak
         withdraw_from,
         deposit_to,
     )?;
+    manual_concat_account_metas(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        &withdraw_quote,
+        &deposit_quote,
+        bridge_stake_seed,
+    )
+}
+
+/// [`manual_concat_get_account_metas()`] for a previously computed [`PoolPairQuote`].
+///
+/// Errs if `pool_pair_quote` is no longer valid, see [`validate_pool_pair_quote()`]
+pub fn manual_concat_get_account_metas_for_quote<
+    W: WithdrawStake + ?Sized,
+    D: DepositStake + ?Sized,
+>(
+    swap_params: &SwapParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    pool_pair_quote: &PoolPairQuote,
+    bridge_stake_seed: u32,
+) -> Result<Vec<AccountMeta>> {
+    validate_pool_pair_quote(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        pool_pair_quote,
+    )?;
+    manual_concat_account_metas(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        &pool_pair_quote.withdraw_quote,
+        &pool_pair_quote.deposit_quote,
+        bridge_stake_seed,
+    )
+}
+
+fn manual_concat_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    swap_params: &SwapParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    withdraw_quote: &WithdrawStakeQuote,
+    deposit_quote: &DepositStakeQuote,
+    bridge_stake_seed: u32,
+) -> Result<Vec<AccountMeta>> {
     let bridge_stake_seed_le_bytes = bridge_stake_seed.to_le_bytes();
     let bridge_stake = find_bridge_stake(
         &swap_params.token_transfer_authority,
@@ -80,7 +136,7 @@ pub fn manual_concat_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStak
             user: swap_params.token_transfer_authority,
             stake_account: bridge_stake,
             dest_token_to: swap_params.destination_token_account,
-            dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+            dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
             dest_token_mint: swap_params.destination_mint,
         });
     if deposit_prefix[DEPOSIT_STAKE_DST_TOKEN_MINT_IDX].pubkey == native_mint::ID {
@@ -88,17 +144,19 @@ pub fn manual_concat_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStak
     }
     Ok(prefund_withdraw_prefix
         .into_iter()
-        .chain(withdraw_from.virtual_ix(&withdraw_quote)?.accounts)
+        .chain(withdraw_from.virtual_ix(withdraw_quote)?.accounts)
         .chain(std::iter::once(swap_params.placeholder_account_meta()))
         .chain(deposit_prefix)
         .chain(
             deposit_to
-                .virtual_ix(&deposit_quote, &deposit_stake_info)?
+                .virtual_ix(deposit_quote, &deposit_stake_info)?
                 .accounts,
         )
         .collect())
 }
 
+/// Recomputes the quote. Use [`prefund_get_account_metas_for_quote()`]
+/// to reuse a [`PoolPairQuote`] instead.
 pub fn prefund_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
     swap_params: &SwapParams,
     prefund_repay_params: &PrefundRepayParams,
@@ -106,7 +164,6 @@ pub fn prefund_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?S
     deposit_to: &D,
     bridge_stake_seed: u32,
 ) -> Result<Vec<AccountMeta>> {
-    // TODO: this is doing the same computation as it did in quote, should we cache this somehow?
     let prefund_split_lamports = prefund_repay_params.prefund_split_lamports()?;
     let (withdraw_quote, deposit_quote) = first_avail_prefund_quote(
         swap_params.in_amount,
@@ -114,6 +171,55 @@ pub fn prefund_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?S
         withdraw_from,
         deposit_to,
     )?;
+    prefund_account_metas(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        &withdraw_quote,
+        &deposit_quote,
+        bridge_stake_seed,
+    )
+}
+
+/// [`prefund_get_account_metas()`] for a previously computed [`PoolPairQuote`].
+///
+/// Errs if `pool_pair_quote` is no longer valid, see [`validate_pool_pair_quote()`]
+pub fn prefund_get_account_metas_for_quote<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    swap_params: &SwapParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    pool_pair_quote: &PoolPairQuote,
+    bridge_stake_seed: u32,
+) -> Result<Vec<AccountMeta>> {
+    validate_pool_pair_quote(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        pool_pair_quote,
+    )?;
+    prefund_account_metas(
+        swap_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        &pool_pair_quote.withdraw_quote,
+        &pool_pair_quote.deposit_quote,
+        bridge_stake_seed,
+    )
+}
+
+fn prefund_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    swap_params: &SwapParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    withdraw_quote: &WithdrawStakeQuote,
+    deposit_quote: &DepositStakeQuote,
+    bridge_stake_seed: u32,
+) -> Result<Vec<AccountMeta>> {
     let bridge_stake_seed_le_bytes = bridge_stake_seed.to_le_bytes();
     let bridge_stake = find_bridge_stake(
         &swap_params.token_transfer_authority,
@@ -129,7 +235,7 @@ pub fn prefund_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?S
             src_token_mint: swap_params.source_mint,
             dest_token_to: swap_params.destination_token_account,
             dest_token_mint: swap_params.destination_mint,
-            dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+            dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
             bridge_stake,
             prefunder: stakedex_program::PREFUNDER_ID,
             slumdog_stake,
@@ -153,28 +259,228 @@ pub fn prefund_get_account_metas<W: WithdrawStake + ?Sized, D: DepositStake + ?S
             metas[mint_idx].is_writable = false;
         }
     }
-    let withdraw_stake_virtual_ix = withdraw_from.virtual_ix(&withdraw_quote)?;
+    let withdraw_stake_virtual_ix = withdraw_from.virtual_ix(withdraw_quote)?;
     metas.extend(withdraw_stake_virtual_ix.accounts);
-    let deposit_stake_virtual_ix = deposit_to.virtual_ix(&deposit_quote, &deposit_stake_info)?;
+    let deposit_stake_virtual_ix = deposit_to.virtual_ix(deposit_quote, &deposit_stake_info)?;
     metas.extend(deposit_stake_virtual_ix.accounts);
     Ok(metas)
 }
 
+/// A [`quote_pool_pair()`] quote along with the intermediate results it was computed from,
+/// so that instructions for the exact same route can be built without recomputing them.
+#[derive(Clone, Debug)]
+pub struct PoolPairQuote {
+    pub quote: Quote,
+
+    /// Withdraw stake quote of the chosen validator, before splitting off prefund lamports
+    pub withdraw_quote: WithdrawStakeQuote,
+
+    pub deposit_quote: DepositStakeQuote,
+
+    /// Total lamports of the slumdog stake that is instant unstaked to repay the prefund flash loan
+    pub slumdog_target_lamports: u64,
+
+    /// Lamports split off the bridge stake into the slumdog stake
+    pub prefund_split_lamports: u64,
+}
+
+impl PoolPairQuote {
+    /// Vote account of the stake withdrawn from `withdraw_from` and deposited into `deposit_to`
+    pub fn voter(&self) -> Pubkey {
+        self.withdraw_quote.voter
+    }
+}
+
+/// The last [`PoolPairQuote`] a pair AMM returned from [`jupiter_amm_interface::Amm::quote()`],
+/// so that [`jupiter_amm_interface::Amm::get_swap_and_account_metas()`] builds the quoted route
+/// instead of whatever route a recomputed quote would pick.
+///
+/// Clones start out with the quote last stored in the original.
+#[derive(Default)]
+pub struct LastPoolPairQuote(ArcSwapOption<(Pubkey, PoolPairQuote)>);
+
+impl Clone for LastPoolPairQuote {
+    fn clone(&self) -> Self {
+        Self(ArcSwapOption::new(self.0.load_full()))
+    }
+}
+
+impl LastPoolPairQuote {
+    pub fn store(&self, input_mint: Pubkey, pool_pair_quote: PoolPairQuote) {
+        self.0.store(Some(Arc::new((input_mint, pool_pair_quote))));
+    }
+
+    /// The last stored quote if it was for `swap_params`' input mint and amount,
+    /// else a freshly computed ExactIn quote for them.
+    ///
+    /// The returned quote still needs to be validated, see [`validate_pool_pair_quote()`]
+    pub fn for_swap<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+        &self,
+        swap_params: &SwapParams,
+        prefund_repay_params: &PrefundRepayParams,
+        withdraw_from: &W,
+        deposit_to: &D,
+    ) -> Result<PoolPairQuote, StakedexSdkError> {
+        if let Some(last) = self.0.load().as_ref() {
+            let (input_mint, pool_pair_quote) = last.as_ref();
+            if *input_mint == swap_params.source_mint
+                && pool_pair_quote.quote.in_amount == swap_params.in_amount
+            {
+                return Ok(pool_pair_quote.clone());
+            }
+        }
+        quote_pool_pair_detailed(
+            &QuoteParams {
+                amount: swap_params.in_amount,
+                input_mint: swap_params.source_mint,
+                output_mint: swap_params.destination_mint,
+                swap_mode: SwapMode::ExactIn,
+            },
+            prefund_repay_params,
+            withdraw_from,
+            deposit_to,
+        )
+    }
+}
+
+/// Handles both [`SwapMode::ExactIn`] and [`SwapMode::ExactOut`].
+///
+/// For ExactOut, the returned quote's `in_amount` is the minimum input amount
+/// whose `out_amount` is at least `quote_params.amount`.
 pub fn quote_pool_pair<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
     quote_params: &QuoteParams,
     prefund_repay_params: &PrefundRepayParams,
     withdraw_from: &W,
     deposit_to: &D,
 ) -> Result<Quote> {
+    Ok(quote_pool_pair_detailed(
+        quote_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+    )?
+    .quote)
+}
+
+/// [`quote_pool_pair()`], but returns the intermediate results as well.
+///
+/// Does not heap-allocate, including on error, if the pools' quote methods don't.
+pub fn quote_pool_pair_detailed<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    quote_params: &QuoteParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+) -> Result<PoolPairQuote, StakedexSdkError> {
+    quote_pool_pair_detailed_for_voters(
+        quote_params,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        &|_| true,
+    )
+}
+
+/// [`quote_pool_pair_detailed()`], but only withdraws stake delegated to
+/// vote accounts that `voter_filter` returns true for
+pub fn quote_pool_pair_detailed_for_voters<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    quote_params: &QuoteParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    voter_filter: &dyn Fn(&Pubkey) -> bool,
+) -> Result<PoolPairQuote, StakedexSdkError> {
+    let in_amount = match quote_params.swap_mode {
+        SwapMode::ExactIn => quote_params.amount,
+        SwapMode::ExactOut => {
+            let out_amount = quote_params.amount;
+            reverse_quote(out_amount, out_amount, |in_amount| {
+                quote_pool_pair_exact_in(
+                    in_amount,
+                    prefund_repay_params,
+                    withdraw_from,
+                    deposit_to,
+                    voter_filter,
+                )
+                .ok()
+                .map(|q| q.quote.out_amount)
+            })
+            .ok_or(ExactOutUnreachableErr)?
+        }
+    };
+    quote_pool_pair_exact_in(
+        in_amount,
+        prefund_repay_params,
+        withdraw_from,
+        deposit_to,
+        voter_filter,
+    )
+}
+
+/// Checks that `pool_pair_quote` can still be executed with `swap_params`:
+/// - `swap_params.in_amount` is the quoted `in_amount`
+/// - the prefund flash loan repayment has not changed
+/// - `withdraw_from` can still accept stake withdrawals
+/// - `withdraw_from` still withdraws the same stake account for `in_amount`
+/// - `deposit_to` still accepts the withdrawn stake account
+pub fn validate_pool_pair_quote<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    swap_params: &SwapParams,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    pool_pair_quote: &PoolPairQuote,
+) -> Result<(), StaleQuoteErr> {
+    if swap_params.in_amount != pool_pair_quote.quote.in_amount {
+        return Err(StaleQuoteErr::AmountMismatch);
+    }
+    match prefund_repay_params.prefund_split_lamports() {
+        Ok(p) if p == pool_pair_quote.prefund_split_lamports => (),
+        _ => return Err(StaleQuoteErr::PrefundChanged),
+    }
+    if !withdraw_from.can_accept_stake_withdrawals() {
+        return Err(StaleQuoteErr::RouteInvalidated);
+    }
+    let mut withdraw_unchanged = false;
+    withdraw_from.try_for_each_withdraw_stake_quote(pool_pair_quote.quote.in_amount, &mut |wsq| {
+        if wsq.voter != pool_pair_quote.voter() {
+            return ControlFlow::Continue(());
+        }
+        let wsq = prefund_transform_wsq(wsq);
+        let quoted = &pool_pair_quote.withdraw_quote;
+        withdraw_unchanged = wsq.lamports_out == quoted.lamports_out
+            && wsq.lamports_staked == quoted.lamports_staked
+            && wsq.fee_amount == quoted.fee_amount;
+        ControlFlow::Break(())
+    });
+    if !withdraw_unchanged {
+        return Err(StaleQuoteErr::RouteInvalidated);
+    }
+    let wsq_after_prefund = prefund_split_wsq(
+        pool_pair_quote.withdraw_quote,
+        pool_pair_quote.prefund_split_lamports,
+    );
+    match deposit_to.get_deposit_stake_quote(wsq_after_prefund) {
+        Ok(dsq) if !dsq.is_zero_out() && dsq.voter == pool_pair_quote.voter() => Ok(()),
+        _ => Err(StaleQuoteErr::RouteInvalidated),
+    }
+}
+
+fn quote_pool_pair_exact_in<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    in_amount: u64,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+    voter_filter: &dyn Fn(&Pubkey) -> bool,
+) -> Result<PoolPairQuote, StakedexSdkError> {
+    let slumdog_target_lamports = prefund_repay_params.slumdog_target_lamports()?;
     let prefund_split_lamports = prefund_repay_params.prefund_split_lamports()?;
-    let (withdraw_quote, deposit_quote) = first_avail_prefund_quote(
-        quote_params.amount,
+    let (withdraw_quote, deposit_quote) = first_avail_prefund_quote_for_voters(
+        in_amount,
         prefund_split_lamports,
         withdraw_from,
         deposit_to,
+        voter_filter,
     )?;
 
-    let in_amount = quote_params.amount;
     let aft_global_fees = if deposit_to.staked_sol_mint() == wsol::ID {
         // no router fees if `deposit_to` is unstake.it pool
         AfterFees {
@@ -196,32 +502,40 @@ pub fn quote_pool_pair<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
     // before global fees + deposit stake fees, after prefund repay, in terms of out token
     let mut approx_before_fees = deposit_quote.tokens_out + deposit_quote.fee_amount;
 
-    let approx_prefund_fee_out_token = approx_fees_charged_out_token(
+    let approx_prefund_fee_out_token = checked_approx_fees_charged_out_token(
         approx_before_fees,
         prefund_split_lamports,
         withdraw_quote.lamports_out,
-    )?;
+    )
+    .ok_or(StakedexSdkError::MathOverflow)?;
     approx_total_fees += approx_prefund_fee_out_token;
     // approx before global fees + deposit stake fees + prefund repay fees, after withdraw stake fees, in terms of out token
     approx_before_fees += approx_prefund_fee_out_token;
 
-    let approx_withdraw_stake_fee_out_token = approx_fees_charged_out_token(
+    let approx_withdraw_stake_fee_out_token = checked_approx_fees_charged_out_token(
         approx_before_fees,
         withdraw_quote.fee_amount,
-        quote_params.amount,
-    )?;
+        in_amount,
+    )
+    .ok_or(StakedexSdkError::MathOverflow)?;
     approx_total_fees += approx_withdraw_stake_fee_out_token;
     approx_before_fees += approx_withdraw_stake_fee_out_token;
 
     let fee_pct = Decimal::from_f64((approx_total_fees as f64) / (approx_before_fees as f64))
         .unwrap_or_else(Decimal::zero);
-    Ok(Quote {
-        in_amount,
-        out_amount,
-        fee_amount: approx_total_fees,
-        fee_pct,
-        fee_mint: deposit_to.staked_sol_mint(),
-        ..Quote::default()
+    Ok(PoolPairQuote {
+        quote: Quote {
+            in_amount,
+            out_amount,
+            fee_amount: approx_total_fees,
+            fee_pct,
+            fee_mint: deposit_to.staked_sol_mint(),
+            ..Quote::default()
+        },
+        withdraw_quote,
+        deposit_quote,
+        slumdog_target_lamports,
+        prefund_split_lamports,
     })
 }
 
@@ -236,6 +550,8 @@ pub(crate) fn prepare_underlying_liquidities(
     }
 }
 
+/// Does not heap-allocate, see [`WithdrawStake::try_for_each_withdraw_stake_quote()`].
+///
 /// Returns
 /// (
 ///   withdraw_stake_quote before splitting off prefund lamports,
@@ -246,26 +562,45 @@ pub fn first_avail_prefund_quote<W: WithdrawStake + ?Sized, D: DepositStake + ?S
     prefund_split_lamports: u64,
     withdraw_from: &W,
     deposit_to: &D,
+) -> Result<(WithdrawStakeQuote, DepositStakeQuote), SwapViaStakeQuoteErr> {
+    first_avail_prefund_quote_for_voters(
+        withdraw_amount,
+        prefund_split_lamports,
+        withdraw_from,
+        deposit_to,
+        &|_| true,
+    )
+}
+
+fn first_avail_prefund_quote_for_voters<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    withdraw_amount: u64,
+    prefund_split_lamports: u64,
+    withdraw_from: &W,
+    deposit_to: &D,
+    voter_filter: &dyn Fn(&Pubkey) -> bool,
 ) -> Result<(WithdrawStakeQuote, DepositStakeQuote), SwapViaStakeQuoteErr> {
     if !withdraw_from.can_accept_stake_withdrawals() {
         return Err(WithdrawStakeQuoteErr::CannotAcceptStakeWithdrawals.into());
     }
-    let withdraw_quote_iter = withdraw_from.withdraw_stake_quote_iter_dyn(withdraw_amount);
-    for wsq in withdraw_quote_iter {
+    let mut res = Err(SwapViaStakeQuoteErr::NoRouteFound);
+    withdraw_from.try_for_each_withdraw_stake_quote(withdraw_amount, &mut |wsq| {
+        if !voter_filter(&wsq.voter) {
+            return ControlFlow::Continue(());
+        }
         let wsq = prefund_transform_wsq(wsq);
-        let mut wsq_after_prefund = wsq;
-        wsq_after_prefund.lamports_out = wsq.lamports_out.saturating_sub(prefund_split_lamports);
-        wsq_after_prefund.lamports_staked =
-            wsq.lamports_staked.saturating_sub(prefund_split_lamports);
+        let wsq_after_prefund = prefund_split_wsq(wsq, prefund_split_lamports);
         if wsq_after_prefund.is_zero_out() {
-            continue;
+            return ControlFlow::Continue(());
         }
-        let dsq = deposit_to.get_deposit_stake_quote(wsq_after_prefund)?;
-        if !dsq.is_zero_out() {
-            return Ok((wsq, dsq));
+        match deposit_to.get_deposit_stake_quote(wsq_after_prefund) {
+            Ok(dsq) if dsq.is_zero_out() => ControlFlow::Continue(()),
+            dsq_res => {
+                res = dsq_res.map(|dsq| (wsq, dsq)).map_err(Into::into);
+                ControlFlow::Break(())
+            }
         }
-    }
-    Err(SwapViaStakeQuoteErr::NoRouteFound)
+    });
+    res
 }
 
 /// Since we're prefunding bridge stake with the rent, we need to add it to the output stake account
@@ -275,9 +610,36 @@ fn prefund_transform_wsq(mut wsq: WithdrawStakeQuote) -> WithdrawStakeQuote {
     wsq
 }
 
+/// Subtracts the lamports split off to the slumdog stake from the bridge stake
+fn prefund_split_wsq(
+    mut wsq: WithdrawStakeQuote,
+    prefund_split_lamports: u64,
+) -> WithdrawStakeQuote {
+    wsq.lamports_out = wsq.lamports_out.saturating_sub(prefund_split_lamports);
+    wsq.lamports_staked = wsq.lamports_staked.saturating_sub(prefund_split_lamports);
+    wsq
+}
+
 /// Calculates approximate fees charged in terms of out token given known
 /// amt_after_fee in terms of out token and fee ratio
-fn approx_fees_charged_out_token(amt_after_fee: u64, fee_num: u64, fee_denom: u64) -> Result<u64> {
+pub fn approx_fees_charged_out_token(
+    amt_after_fee: u64,
+    fee_num: u64,
+    fee_denom: u64,
+) -> Result<u64> {
+    if fee_num > fee_denom {
+        return Err(anyhow!("100% withdrawal fees"));
+    }
+    checked_approx_fees_charged_out_token(amt_after_fee, fee_num, fee_denom)
+        .ok_or_else(|| anyhow!("Math error"))
+}
+
+/// [`approx_fees_charged_out_token()`] that returns `None` on error instead of allocating one
+fn checked_approx_fees_charged_out_token(
+    amt_after_fee: u64,
+    fee_num: u64,
+    fee_denom: u64,
+) -> Option<u64> {
     // fee_rate = fee_num / fee_denom
     // (1.0 - fee_rate) * amt_before_fee = amt_after_fee
     // amt_before_fee = amt_after_fee / (1.0 - fee_rate)
@@ -287,13 +649,10 @@ fn approx_fees_charged_out_token(amt_after_fee: u64, fee_num: u64, fee_denom: u6
     // = fee_denom * amt_after_fee / (fee_denom - fee_num) - amt_after_fee
     // = amt_after_fee (fee_denom / (fee_denom - fee_num) - 1)
     // = amt_after_fee * fee_num / (fee_denom - fee_num)
-    let denom = fee_denom
-        .checked_sub(fee_num)
-        .ok_or_else(|| anyhow!("100% withdrawal fees"))?;
+    let denom = fee_denom.checked_sub(fee_num)?;
     (amt_after_fee as u128 * fee_num as u128)
         .checked_div(denom as u128)
         .and_then(|v| u64::try_from(v).ok())
-        .ok_or_else(|| anyhow!("Math error"))
 }
 
 /// TODO: this should really be in unstake-lib instead.
@@ -1,10 +1,12 @@
 //! A pair of stake pools that can (Prefund)SwapViaStake with each other
 
+mod bridge_seed;
 mod common;
 mod one_way;
 mod prefund;
 mod two_way;
 
+pub use bridge_seed::*;
 pub use common::*;
 pub use one_way::*;
 pub use prefund::*;
@@ -7,24 +7,28 @@ use solana_sdk::pubkey::Pubkey;
 use stakedex_interface::PREFUND_SWAP_VIA_STAKE_IX_ACCOUNTS_LEN;
 use stakedex_sdk_common::{
     find_stake_pool_pair_amm_key, spl_deposit_cap_guard_program, unstake_it_program, DepositStake,
-    WithdrawStake, TEMPORARY_JUP_AMM_LABEL,
+    StakedexSdkError, WithdrawStake, TEMPORARY_JUP_AMM_LABEL,
 };
 use std::collections::HashSet;
 
 use crate::{
-    jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, manual_concat_get_account_metas,
-    prepare_underlying_liquidities, quote_pool_pair, PrefundRepayParams,
+    jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, manual_concat_get_account_metas_for_quote,
+    prepare_underlying_liquidities, quote_pool_pair_detailed, BridgeSeedSource, LastPoolPairQuote,
+    PrefundRepayParams, SharedPool,
 };
 
+/// See [`crate::TwoWayPoolPair`] for how pool state is shared between pairs
 #[derive(Clone)]
 pub struct OneWayPoolPair<
     W: WithdrawStake + Clone + Send + Sync + 'static,
     D: DepositStake + Clone + Send + Sync + 'static,
 > {
-    pub withdraw: W,
-    pub deposit: D,
+    pub withdraw: SharedPool<W>,
+    pub deposit: SharedPool<D>,
     prefund_repay_params: Option<PrefundRepayParams>,
     underlying_liquidities: Option<HashSet<Pubkey>>,
+    last_quote: LastPoolPairQuote,
+    bridge_seed_source: Option<BridgeSeedSource>,
 }
 
 impl<W, D> OneWayPoolPair<W, D>
@@ -32,16 +36,32 @@ where
     W: WithdrawStake + Clone + Send + Sync,
     D: DepositStake + Clone + Send + Sync,
 {
-    pub fn new(withdraw: W, deposit: D) -> Self {
+    pub fn new(withdraw: SharedPool<W>, deposit: SharedPool<D>) -> Self {
         let underlying_liquidities = prepare_underlying_liquidities(&[
-            withdraw.underlying_liquidity(),
-            deposit.underlying_liquidity(),
+            withdraw.load().underlying_liquidity(),
+            deposit.load().underlying_liquidity(),
         ]);
         Self {
             withdraw,
             deposit,
             prefund_repay_params: None,
             underlying_liquidities,
+            last_quote: LastPoolPairQuote::default(),
+            bridge_seed_source: None,
+        }
+    }
+
+    /// Picks swaps' `bridge_stake_seed`s with `bridge_seed_source` instead of at random,
+    /// e.g. [`crate::BridgeSeedAllocator::into_source()`] to skip seeds whose accounts exist
+    pub fn with_bridge_seed_source(mut self, bridge_seed_source: BridgeSeedSource) -> Self {
+        self.bridge_seed_source = Some(bridge_seed_source);
+        self
+    }
+
+    fn bridge_stake_seed(&self, user: &Pubkey) -> Result<u32, StakedexSdkError> {
+        match self.bridge_seed_source.as_ref() {
+            Some(source) => source(user),
+            None => Ok(rand::random()),
         }
     }
 
@@ -70,23 +90,23 @@ where
 
     fn key(&self) -> Pubkey {
         find_stake_pool_pair_amm_key(
-            &self.withdraw.main_state_key(),
-            &self.deposit.main_state_key(),
+            &self.withdraw.load().main_state_key(),
+            &self.deposit.load().main_state_key(),
         )
         .0
     }
 
     fn get_reserve_mints(&self) -> Vec<Pubkey> {
         Vec::from([
-            self.withdraw.staked_sol_mint(),
-            self.deposit.staked_sol_mint(),
+            self.withdraw.load().staked_sol_mint(),
+            self.deposit.load().staked_sol_mint(),
         ])
     }
 
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
         [
-            self.withdraw.get_accounts_to_update().as_slice(),
-            self.deposit.get_accounts_to_update().as_slice(),
+            self.withdraw.load().get_accounts_to_update().as_slice(),
+            self.deposit.load().get_accounts_to_update().as_slice(),
             PrefundRepayParams::ACCOUNTS_TO_UPDATE.as_slice(),
         ]
         .concat()
@@ -94,6 +114,7 @@ where
 
     fn update(&mut self, account_map: &AccountMap) -> Result<()> {
         // TODO: not sure if should short-circuit and early return if first update() fails
+        // no-op for pools another Amm already updated with the same accounts
         let rw = self.withdraw.update(account_map);
         let rd = self.deposit.update(account_map);
         let rp = match self.prefund_repay_params.as_mut() {
@@ -109,32 +130,46 @@ where
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        if quote_params.input_mint != self.withdraw.staked_sol_mint()
-            || quote_params.output_mint != self.deposit.staked_sol_mint()
+        let (withdraw, deposit) = (self.withdraw.load(), self.deposit.load());
+        if quote_params.input_mint != withdraw.staked_sol_mint()
+            || quote_params.output_mint != deposit.staked_sol_mint()
         {
-            Err(anyhow!(
+            return Err(anyhow!(
                 "Cannot handle {} -> {}",
                 quote_params.input_mint,
                 quote_params.output_mint
-            ))
-        } else {
-            quote_pool_pair(
-                quote_params,
-                self.prefund_repay_params_checked()?,
-                &self.withdraw,
-                &self.deposit,
-            )
+            ));
         }
+        let pool_pair_quote = quote_pool_pair_detailed(
+            quote_params,
+            self.prefund_repay_params_checked()?,
+            withdraw.as_ref(),
+            deposit.as_ref(),
+        )?;
+        let quote = pool_pair_quote.quote.clone();
+        self.last_quote
+            .store(quote_params.input_mint, pool_pair_quote);
+        Ok(quote)
     }
 
+    /// Builds the route of the last quote if it was for the same swap, see [`LastPoolPairQuote`]
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
-        let bridge_stake_seed = rand::random();
+        let bridge_stake_seed = self.bridge_stake_seed(&swap_params.token_transfer_authority)?;
+        let (withdraw, deposit) = (self.withdraw.load(), self.deposit.load());
+        let prefund_repay_params = self.prefund_repay_params_checked()?;
+        let pool_pair_quote = self.last_quote.for_swap(
+            swap_params,
+            prefund_repay_params,
+            withdraw.as_ref(),
+            deposit.as_ref(),
+        )?;
         let mut account_metas = vec![STAKEDEX_ACCOUNT_META.clone()];
-        account_metas.extend(manual_concat_get_account_metas(
+        account_metas.extend(manual_concat_get_account_metas_for_quote(
             swap_params,
-            self.prefund_repay_params_checked()?,
-            &self.withdraw,
-            &self.deposit,
+            prefund_repay_params,
+            withdraw.as_ref(),
+            deposit.as_ref(),
+            &pool_pair_quote,
             bridge_stake_seed,
         )?);
         account_metas.push(swap_params.placeholder_account_meta());
@@ -152,14 +187,18 @@ where
         stakedex_interface::ID
     }
 
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
     fn unidirectional(&self) -> bool {
         true
     }
 
     fn get_accounts_len(&self) -> usize {
         1 + PREFUND_SWAP_VIA_STAKE_IX_ACCOUNTS_LEN
-            + self.withdraw.accounts_len()
-            + self.deposit.accounts_len()
+            + self.withdraw.load().accounts_len()
+            + self.deposit.load().accounts_len()
     }
 
     fn underlying_liquidities(&self) -> Option<HashSet<Pubkey>> {
@@ -167,14 +206,15 @@ where
     }
 
     fn program_dependencies(&self) -> Vec<(Pubkey, String)> {
+        let (withdraw, deposit) = (self.withdraw.load(), self.deposit.load());
         vec![
             (
-                self.withdraw.program_id(),
-                self.withdraw.stake_pool_label().to_lowercase(),
+                withdraw.program_id(),
+                withdraw.stake_pool_label().to_lowercase(),
             ),
             (
-                self.deposit.program_id(),
-                self.deposit.stake_pool_label().to_lowercase(),
+                deposit.program_id(),
+                deposit.stake_pool_label().to_lowercase(),
             ),
             (unstake_it_program::ID, "unstake.it".to_owned()),
             (
@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use jupiter_amm_interface::AccountMap;
 use solana_sdk::{account::Account, pubkey::Pubkey};
 use stakedex_sdk_common::{
-    unstake_it_pool, unstake_it_program, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
+    unstake_it_pool, unstake_it_program, StakedexSdkError, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
     ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS,
 };
 use unstake_interface::{
@@ -64,20 +64,14 @@ impl PrefundRepayParams {
 
     /// Computes the total lamports (including rent) that the slumdog stake account
     /// should consist of when it gets instant unstaked in order to repay the prefund flash loan
-    pub fn slumdog_target_lamports(&self) -> Result<u64> {
-        let lamports_required = PREFUND_FLASH_LOAN_LAMPORTS;
-        if self.sol_reserves_lamports < lamports_required + ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS {
-            return Err(anyhow!("Not enough liquidity for slumdog instant unstake"));
-        }
-        self.fee
-            .pseudo_reverse(ReverseFeeArgs {
-                pool_balance: PoolBalance {
-                    pool_incoming_stake: self.incoming_stake,
-                    sol_reserves_lamports: self.sol_reserves_lamports,
-                },
-                lamports_after_fee: lamports_required,
-            })
-            .ok_or_else(|| anyhow!("pseudo_reverse() MathError"))
+    pub fn slumdog_target_lamports(&self) -> Result<u64, StakedexSdkError> {
+        slumdog_target_lamports(
+            &self.fee,
+            PoolBalance {
+                pool_incoming_stake: self.incoming_stake,
+                sol_reserves_lamports: self.sol_reserves_lamports,
+            },
+        )
     }
 
     /// Computes the lamports that must be split off from bridge_stake to slumdog_stake in order to
@@ -90,12 +84,29 @@ impl PrefundRepayParams {
     /// The stake account instant unstaked to repay the flash loan will comprise
     /// - return value staked lamports
     /// - STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS unstaked lamports
-    pub fn prefund_split_lamports(&self) -> Result<u64> {
+    pub fn prefund_split_lamports(&self) -> Result<u64, StakedexSdkError> {
         let slumdog_target_lamports = self.slumdog_target_lamports()?;
         Ok(slumdog_target_lamports.saturating_sub(STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS))
     }
 }
 
+/// [`PrefundRepayParams::slumdog_target_lamports()`] for borrowed unstake.it pool state,
+/// e.g. to avoid copying `fee` into a [`PrefundRepayParams`] on every quote
+pub fn slumdog_target_lamports(
+    fee: &FeeEnum,
+    pool_balance: PoolBalance,
+) -> Result<u64, StakedexSdkError> {
+    let lamports_required = PREFUND_FLASH_LOAN_LAMPORTS;
+    if pool_balance.sol_reserves_lamports < lamports_required + ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS {
+        return Err(StakedexSdkError::InsufficientReserveLiquidity);
+    }
+    fee.pseudo_reverse(ReverseFeeArgs {
+        pool_balance,
+        lamports_after_fee: lamports_required,
+    })
+    .ok_or(StakedexSdkError::MathOverflow)
+}
+
 fn extract_fee_enum(accounts_map: &AccountMap) -> Result<FeeEnum> {
     let fee_acc = accounts_map
         .get(&unstake_it_program::FEE_ID)
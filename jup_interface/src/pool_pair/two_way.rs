@@ -7,24 +7,29 @@ use solana_sdk::pubkey::Pubkey;
 use stakedex_interface::PREFUND_SWAP_VIA_STAKE_IX_ACCOUNTS_LEN;
 use stakedex_sdk_common::{
     find_stake_pool_pair_amm_key, spl_deposit_cap_guard_program, unstake_it_program, DepositStake,
-    WithdrawStake, TEMPORARY_JUP_AMM_LABEL,
+    StakedexSdkError, WithdrawStake, TEMPORARY_JUP_AMM_LABEL,
 };
 use std::collections::HashSet;
 
 use crate::{
-    jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, manual_concat_get_account_metas,
-    prepare_underlying_liquidities, quote_pool_pair, PrefundRepayParams,
+    jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, manual_concat_get_account_metas_for_quote,
+    prepare_underlying_liquidities, quote_pool_pair_detailed, BridgeSeedSource, LastPoolPairQuote,
+    PrefundRepayParams, SharedPool,
 };
 
+/// Pools are [`SharedPool`]s so that Amms containing the same pool share its state,
+/// which is updated by whichever of them is updated with new account data first.
 #[derive(Clone)]
 pub struct TwoWayPoolPair<
     P1: DepositStake + WithdrawStake + Clone + Send + Sync + 'static,
     P2: DepositStake + WithdrawStake + Clone + Send + Sync + 'static,
 > {
-    pub p1: P1,
-    pub p2: P2,
+    pub p1: SharedPool<P1>,
+    pub p2: SharedPool<P2>,
     prefund_repay_params: Option<PrefundRepayParams>,
     underlying_liquidities: Option<HashSet<Pubkey>>,
+    last_quote: LastPoolPairQuote,
+    bridge_seed_source: Option<BridgeSeedSource>,
 }
 
 impl<P1, P2> TwoWayPoolPair<P1, P2>
@@ -32,18 +37,35 @@ where
     P1: DepositStake + WithdrawStake + Clone + Send + Sync,
     P2: DepositStake + WithdrawStake + Clone + Send + Sync,
 {
-    pub fn new(p1: P1, p2: P2) -> Self {
+    pub fn new(p1: SharedPool<P1>, p2: SharedPool<P2>) -> Self {
+        let (p1_state, p2_state) = (p1.load(), p2.load());
         let underlying_liquidities = prepare_underlying_liquidities(&[
-            DepositStake::underlying_liquidity(&p1),
-            <dyn WithdrawStake>::underlying_liquidity(&p1),
-            DepositStake::underlying_liquidity(&p2),
-            <dyn WithdrawStake>::underlying_liquidity(&p2),
+            DepositStake::underlying_liquidity(p1_state.as_ref()),
+            <dyn WithdrawStake>::underlying_liquidity(p1_state.as_ref()),
+            DepositStake::underlying_liquidity(p2_state.as_ref()),
+            <dyn WithdrawStake>::underlying_liquidity(p2_state.as_ref()),
         ]);
         Self {
             p1,
             p2,
             prefund_repay_params: None,
             underlying_liquidities,
+            last_quote: LastPoolPairQuote::default(),
+            bridge_seed_source: None,
+        }
+    }
+
+    /// Picks swaps' `bridge_stake_seed`s with `bridge_seed_source` instead of at random,
+    /// e.g. [`crate::BridgeSeedAllocator::into_source()`] to skip seeds whose accounts exist
+    pub fn with_bridge_seed_source(mut self, bridge_seed_source: BridgeSeedSource) -> Self {
+        self.bridge_seed_source = Some(bridge_seed_source);
+        self
+    }
+
+    fn bridge_stake_seed(&self, user: &Pubkey) -> Result<u32, StakedexSdkError> {
+        match self.bridge_seed_source.as_ref() {
+            Some(source) => source(user),
+            None => Ok(rand::random()),
         }
     }
 
@@ -71,17 +93,24 @@ where
     }
 
     fn key(&self) -> Pubkey {
-        find_stake_pool_pair_amm_key(&self.p1.main_state_key(), &self.p2.main_state_key()).0
+        find_stake_pool_pair_amm_key(
+            &self.p1.load().main_state_key(),
+            &self.p2.load().main_state_key(),
+        )
+        .0
     }
 
     fn get_reserve_mints(&self) -> Vec<Pubkey> {
-        Vec::from([self.p1.staked_sol_mint(), self.p2.staked_sol_mint()])
+        Vec::from([
+            self.p1.load().staked_sol_mint(),
+            self.p2.load().staked_sol_mint(),
+        ])
     }
 
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
         [
-            self.p1.get_accounts_to_update().as_slice(),
-            self.p2.get_accounts_to_update().as_slice(),
+            self.p1.load().get_accounts_to_update().as_slice(),
+            self.p2.load().get_accounts_to_update().as_slice(),
             PrefundRepayParams::ACCOUNTS_TO_UPDATE.as_slice(),
         ]
         .concat()
@@ -89,6 +118,7 @@ where
 
     fn update(&mut self, account_map: &AccountMap) -> Result<()> {
         // TODO: not sure if should short-circuit and early return if first update() fails
+        // no-op for pools another Amm already updated with the same accounts
         let r1 = self.p1.update(account_map);
         let r2 = self.p2.update(account_map);
         let rp = match self.prefund_repay_params.as_mut() {
@@ -104,54 +134,77 @@ where
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        if quote_params.input_mint == self.p1.staked_sol_mint()
-            && quote_params.output_mint == self.p2.staked_sol_mint()
+        let (p1, p2) = (self.p1.load(), self.p2.load());
+        let pool_pair_quote = if quote_params.input_mint == p1.staked_sol_mint()
+            && quote_params.output_mint == p2.staked_sol_mint()
         {
-            quote_pool_pair(
+            quote_pool_pair_detailed(
                 quote_params,
                 self.prefund_repay_params_checked()?,
-                &self.p1,
-                &self.p2,
-            )
-        } else if quote_params.input_mint == self.p2.staked_sol_mint()
-            && quote_params.output_mint == self.p1.staked_sol_mint()
+                p1.as_ref(),
+                p2.as_ref(),
+            )?
+        } else if quote_params.input_mint == p2.staked_sol_mint()
+            && quote_params.output_mint == p1.staked_sol_mint()
         {
-            quote_pool_pair(
+            quote_pool_pair_detailed(
                 quote_params,
                 self.prefund_repay_params_checked()?,
-                &self.p2,
-                &self.p1,
-            )
+                p2.as_ref(),
+                p1.as_ref(),
+            )?
         } else {
-            Err(anyhow!(
+            return Err(anyhow!(
                 "Cannot handle {} -> {}",
                 quote_params.input_mint,
                 quote_params.output_mint
-            ))
-        }
+            ));
+        };
+        let quote = pool_pair_quote.quote.clone();
+        self.last_quote
+            .store(quote_params.input_mint, pool_pair_quote);
+        Ok(quote)
     }
 
+    /// Builds the route of the last quote if it was for the same swap, see [`LastPoolPairQuote`]
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
-        let bridge_stake_seed = rand::random();
+        let bridge_stake_seed = self.bridge_stake_seed(&swap_params.token_transfer_authority)?;
         let mut account_metas = vec![STAKEDEX_ACCOUNT_META.clone()];
-        let other_account_metas = if swap_params.source_mint == self.p1.staked_sol_mint()
-            && swap_params.destination_mint == self.p2.staked_sol_mint()
+        let (p1, p2) = (self.p1.load(), self.p2.load());
+        let other_account_metas = if swap_params.source_mint == p1.staked_sol_mint()
+            && swap_params.destination_mint == p2.staked_sol_mint()
         {
-            manual_concat_get_account_metas(
+            let prefund_repay_params = self.prefund_repay_params_checked()?;
+            let pool_pair_quote = self.last_quote.for_swap(
                 swap_params,
-                self.prefund_repay_params_checked()?,
-                &self.p1,
-                &self.p2,
+                prefund_repay_params,
+                p1.as_ref(),
+                p2.as_ref(),
+            )?;
+            manual_concat_get_account_metas_for_quote(
+                swap_params,
+                prefund_repay_params,
+                p1.as_ref(),
+                p2.as_ref(),
+                &pool_pair_quote,
                 bridge_stake_seed,
             )?
-        } else if swap_params.source_mint == self.p2.staked_sol_mint()
-            && swap_params.destination_mint == self.p1.staked_sol_mint()
+        } else if swap_params.source_mint == p2.staked_sol_mint()
+            && swap_params.destination_mint == p1.staked_sol_mint()
         {
-            manual_concat_get_account_metas(
+            let prefund_repay_params = self.prefund_repay_params_checked()?;
+            let pool_pair_quote = self.last_quote.for_swap(
                 swap_params,
-                self.prefund_repay_params_checked()?,
-                &self.p2,
-                &self.p1,
+                prefund_repay_params,
+                p2.as_ref(),
+                p1.as_ref(),
+            )?;
+            manual_concat_get_account_metas_for_quote(
+                swap_params,
+                prefund_repay_params,
+                p2.as_ref(),
+                p1.as_ref(),
+                &pool_pair_quote,
                 bridge_stake_seed,
             )?
         } else {
@@ -177,11 +230,15 @@ where
         stakedex_interface::ID
     }
 
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
     fn get_accounts_len(&self) -> usize {
         // Pick a single direction
         1 + PREFUND_SWAP_VIA_STAKE_IX_ACCOUNTS_LEN
-            + <dyn WithdrawStake>::accounts_len(&self.p1)
-            + DepositStake::accounts_len(&self.p2)
+            + <dyn WithdrawStake>::accounts_len(self.p1.load().as_ref())
+            + DepositStake::accounts_len(self.p2.load().as_ref())
             + 1
     }
 
@@ -190,15 +247,10 @@ where
     }
 
     fn program_dependencies(&self) -> Vec<(Pubkey, String)> {
+        let (p1, p2) = (self.p1.load(), self.p2.load());
         vec![
-            (
-                self.p1.program_id(),
-                self.p1.stake_pool_label().to_lowercase(),
-            ),
-            (
-                self.p2.program_id(),
-                self.p2.stake_pool_label().to_lowercase(),
-            ),
+            (p1.program_id(), p1.stake_pool_label().to_lowercase()),
+            (p2.program_id(), p2.stake_pool_label().to_lowercase()),
             (unstake_it_program::ID, "unstake.it".to_owned()),
             (
                 spl_deposit_cap_guard_program::ID,
@@ -1,28 +1,28 @@
 use anyhow::{anyhow, Result};
 use jupiter_amm_interface::{
     AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas,
-    SwapParams,
+    SwapMode, SwapParams,
 };
 use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, system_program};
 use spl_token::native_mint;
 use stakedex_interface::{StakeWrappedSolKeys, STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN};
 use stakedex_sdk_common::{
-    find_deposit_stake_amm_key, find_fee_token_acc, spl_deposit_cap_guard_program,
-    stakedex_program, wsol_bridge_in, DepositSol, InitFromKeyedAccount, TEMPORARY_JUP_AMM_LABEL,
+    fee_token_acc, find_deposit_stake_amm_key, spl_deposit_cap_guard_program, stakedex_program,
+    wsol_bridge_in, DepositSol, InitFromKeyedAccount, TEMPORARY_JUP_AMM_LABEL,
 };
 
-use crate::jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META;
+use crate::{jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, SharedPool};
 
 // newtype pattern in order to impl external trait (Amm) on external generic (DepositSol)
 #[derive(Clone)]
-pub struct DepositSolWrapper<T: DepositSol + Clone + Send + Sync + 'static>(pub T);
+pub struct DepositSolWrapper<T: DepositSol + Clone + Send + Sync + 'static>(pub SharedPool<T>);
 
 impl<T> Amm for DepositSolWrapper<T>
 where
     T: DepositSol + InitFromKeyedAccount + Clone + Send + Sync,
 {
     fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
-        T::from_keyed_account(keyed_account, amm_context).map(|t| Self(t))
+        T::from_keyed_account(keyed_account, amm_context).map(|t| Self(SharedPool::new(t)))
     }
 
     fn label(&self) -> String {
@@ -32,15 +32,15 @@ where
     // To avoid key clashes with existing stake pools on jup (Marinade),
     // we can use a PDA like this
     fn key(&self) -> Pubkey {
-        find_deposit_stake_amm_key(&self.0.main_state_key()).0
+        find_deposit_stake_amm_key(&self.0.load().main_state_key()).0
     }
 
     fn get_reserve_mints(&self) -> Vec<Pubkey> {
-        Vec::from([native_mint::ID, self.0.staked_sol_mint()])
+        Vec::from([native_mint::ID, self.0.load().staked_sol_mint()])
     }
 
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        self.0.get_accounts_to_update()
+        self.0.load().get_accounts_to_update()
     }
 
     fn update(&mut self, accounts_map: &AccountMap) -> Result<()> {
@@ -48,8 +48,9 @@ where
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let pool = self.0.load();
         if quote_params.input_mint != native_mint::ID
-            || quote_params.output_mint != self.0.staked_sol_mint()
+            || quote_params.output_mint != pool.staked_sol_mint()
         {
             return Err(anyhow!(
                 "Cannot handle {} -> {}",
@@ -57,12 +58,16 @@ where
                 quote_params.output_mint
             ));
         }
-        let deposit_sol_quote = self.0.get_deposit_sol_quote(quote_params.amount)?;
-        let quote = self.0.convert_quote(deposit_sol_quote);
+        let deposit_sol_quote = match quote_params.swap_mode {
+            SwapMode::ExactIn => pool.get_deposit_sol_quote(quote_params.amount)?,
+            SwapMode::ExactOut => pool.get_deposit_sol_quote_exact_out(quote_params.amount)?,
+        };
+        let quote = pool.convert_quote(deposit_sol_quote);
         Ok(quote)
     }
 
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+        let pool = self.0.load();
         let mut account_metas = vec![STAKEDEX_ACCOUNT_META.clone()];
         account_metas.extend(<[AccountMeta; STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN]>::from(
             StakeWrappedSolKeys {
@@ -75,10 +80,10 @@ where
                 system_program: system_program::ID,
                 wsol_bridge_in: wsol_bridge_in::ID,
                 sol_bridge_out: stakedex_program::SOL_BRIDGE_OUT_ID,
-                dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+                dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
             },
         ));
-        let deposit_sol_virtual_ix = self.0.virtual_ix()?;
+        let deposit_sol_virtual_ix = pool.virtual_ix()?;
         account_metas.extend(deposit_sol_virtual_ix.accounts);
         account_metas.push(swap_params.placeholder_account_meta());
         Ok(SwapAndAccountMetas {
@@ -95,20 +100,22 @@ where
         stakedex_interface::ID
     }
 
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
     fn unidirectional(&self) -> bool {
         true
     }
 
     fn get_accounts_len(&self) -> usize {
-        1 + STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN + self.0.accounts_len()
+        1 + STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN + self.0.load().accounts_len()
     }
 
     fn program_dependencies(&self) -> Vec<(Pubkey, String)> {
+        let pool = self.0.load();
         vec![
-            (
-                self.0.program_id(),
-                self.0.stake_pool_label().to_lowercase(),
-            ),
+            (pool.program_id(), pool.stake_pool_label().to_lowercase()),
             (
                 spl_deposit_cap_guard_program::ID,
                 "spl_deposit_cap_guard".to_owned(),
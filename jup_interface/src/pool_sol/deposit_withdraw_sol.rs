@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use jupiter_amm_interface::{
     AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas,
-    SwapParams,
+    SwapMode, SwapParams,
 };
 use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, system_program};
 use spl_token::native_mint;
@@ -10,22 +10,22 @@ use stakedex_interface::{
     WITHDRAW_WRAPPED_SOL_IX_ACCOUNTS_LEN,
 };
 use stakedex_sdk_common::{
-    find_deposit_stake_amm_key, find_fee_token_acc, stakedex_program, wsol_bridge_in, DepositSol,
+    fee_token_acc, find_deposit_stake_amm_key, stakedex_program, wsol_bridge_in, DepositSol,
     InitFromKeyedAccount, WithdrawSol, TEMPORARY_JUP_AMM_LABEL,
 };
 
-use crate::jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META;
+use crate::{jupiter_stakedex_interface::STAKEDEX_ACCOUNT_META, SharedPool};
 
 // newtype pattern in order to impl external trait (Amm) on external generic (WithdrawSol)
 #[derive(Clone)]
-pub struct DepositWithdrawSolWrapper<T>(pub T);
+pub struct DepositWithdrawSolWrapper<T>(pub SharedPool<T>);
 
 impl<T> Amm for DepositWithdrawSolWrapper<T>
 where
     T: DepositSol + WithdrawSol + InitFromKeyedAccount + Clone + Send + Sync + 'static,
 {
     fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
-        T::from_keyed_account(keyed_account, amm_context).map(|t| Self(t))
+        T::from_keyed_account(keyed_account, amm_context).map(|t| Self(SharedPool::new(t)))
     }
 
     fn label(&self) -> String {
@@ -35,15 +35,15 @@ where
     // To avoid key clashes with existing stake pools on jup (Marinade),
     // we can use a PDA like this
     fn key(&self) -> Pubkey {
-        find_deposit_stake_amm_key(&self.0.main_state_key()).0
+        find_deposit_stake_amm_key(&self.0.load().main_state_key()).0
     }
 
     fn get_reserve_mints(&self) -> Vec<Pubkey> {
-        Vec::from([native_mint::ID, self.0.staked_sol_mint()])
+        Vec::from([native_mint::ID, self.0.load().staked_sol_mint()])
     }
 
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        self.0.get_accounts_to_update()
+        self.0.load().get_accounts_to_update()
     }
 
     fn update(&mut self, accounts_map: &AccountMap) -> Result<()> {
@@ -51,19 +51,26 @@ where
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let pool = self.0.load();
         if quote_params.input_mint == native_mint::ID
-            && quote_params.output_mint == self.0.staked_sol_mint()
+            && quote_params.output_mint == pool.staked_sol_mint()
         {
             // deposit case
-            let deposit_sol_quote = self.0.get_deposit_sol_quote(quote_params.amount)?;
-            let quote = DepositSol::convert_quote(&self.0, deposit_sol_quote);
+            let deposit_sol_quote = match quote_params.swap_mode {
+                SwapMode::ExactIn => pool.get_deposit_sol_quote(quote_params.amount)?,
+                SwapMode::ExactOut => pool.get_deposit_sol_quote_exact_out(quote_params.amount)?,
+            };
+            let quote = DepositSol::convert_quote(pool.as_ref(), deposit_sol_quote);
             Ok(quote)
-        } else if quote_params.input_mint == self.0.staked_sol_mint()
+        } else if quote_params.input_mint == pool.staked_sol_mint()
             && quote_params.output_mint == native_mint::ID
         {
             // withdraw case
-            let withdraw_sol_quote = self.0.get_withdraw_sol_quote(quote_params.amount)?;
-            let quote = WithdrawSol::convert_quote(&self.0, withdraw_sol_quote);
+            let withdraw_sol_quote = match quote_params.swap_mode {
+                SwapMode::ExactIn => pool.get_withdraw_sol_quote(quote_params.amount)?,
+                SwapMode::ExactOut => pool.get_withdraw_sol_quote_exact_out(quote_params.amount)?,
+            };
+            let quote = WithdrawSol::convert_quote(pool.as_ref(), withdraw_sol_quote);
             Ok(quote)
         } else {
             Err(anyhow!(
@@ -75,10 +82,11 @@ where
     }
 
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+        let pool = self.0.load();
         let mut account_metas = vec![STAKEDEX_ACCOUNT_META.clone()];
 
         if swap_params.source_mint == native_mint::ID
-            && swap_params.destination_mint == self.0.staked_sol_mint()
+            && swap_params.destination_mint == pool.staked_sol_mint()
         {
             // deposit case
             account_metas.extend(<[AccountMeta; STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN]>::from(
@@ -92,19 +100,18 @@ where
                     system_program: system_program::ID,
                     wsol_bridge_in: wsol_bridge_in::ID,
                     sol_bridge_out: stakedex_program::SOL_BRIDGE_OUT_ID,
-                    dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint)
-                        .0,
+                    dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
                 },
             ));
 
-            let deposit_sol_virtual_ix = DepositSol::virtual_ix(&self.0)?;
+            let deposit_sol_virtual_ix = DepositSol::virtual_ix(pool.as_ref())?;
             account_metas.extend(deposit_sol_virtual_ix.accounts);
             account_metas.push(swap_params.placeholder_account_meta());
             Ok(SwapAndAccountMetas {
                 swap: Swap::StakeDexStakeWrappedSol,
                 account_metas,
             })
-        } else if swap_params.source_mint == self.0.staked_sol_mint()
+        } else if swap_params.source_mint == pool.staked_sol_mint()
             && swap_params.destination_mint == native_mint::ID
         {
             // withdraw case
@@ -113,14 +120,14 @@ where
                     user: swap_params.token_transfer_authority,
                     src_token_from: swap_params.source_token_account,
                     wsol_to: swap_params.destination_token_account,
-                    wsol_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+                    wsol_fee_token_account: fee_token_acc(&swap_params.destination_mint),
                     src_token_mint: swap_params.source_mint,
                     wsol_mint: swap_params.destination_mint,
                     token_program: spl_token::ID,
                 },
             ));
 
-            let withdraw_sol_virtual_ix = WithdrawSol::virtual_ix(&self.0)?;
+            let withdraw_sol_virtual_ix = WithdrawSol::virtual_ix(pool.as_ref())?;
             account_metas.extend(withdraw_sol_virtual_ix.accounts);
             account_metas.push(swap_params.placeholder_account_meta());
             Ok(SwapAndAccountMetas {
@@ -144,6 +151,10 @@ where
         stakedex_interface::ID
     }
 
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
     // TODO: for compile time max calculation
     //  - should this just be all within const
     //  - or just ditch const altogether since it's either:
@@ -151,6 +162,7 @@ where
     //    - 1 + WITHDRAW_WRAPPED_SOL_IX_ACCOUNTS_LEN + WithdrawSol::accounts_len(),
     //    - and never the other way around
     fn get_accounts_len(&self) -> usize {
+        let pool = self.0.load();
         1 + const {
             if STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN > WITHDRAW_WRAPPED_SOL_IX_ACCOUNTS_LEN {
                 STAKE_WRAPPED_SOL_IX_ACCOUNTS_LEN
@@ -158,15 +170,13 @@ where
                 WITHDRAW_WRAPPED_SOL_IX_ACCOUNTS_LEN
             }
         } + std::cmp::max(
-            WithdrawSol::accounts_len(&self.0),
-            DepositSol::accounts_len(&self.0),
+            WithdrawSol::accounts_len(pool.as_ref()),
+            DepositSol::accounts_len(pool.as_ref()),
         )
     }
 
     fn program_dependencies(&self) -> Vec<(Pubkey, String)> {
-        vec![(
-            self.0.program_id(),
-            self.0.stake_pool_label().to_lowercase(),
-        )]
+        let pool = self.0.load();
+        vec![(pool.program_id(), pool.stake_pool_label().to_lowercase())]
     }
 }
@@ -0,0 +1,76 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use anyhow::Result;
+use jupiter_amm_interface::AccountMap;
+use solana_sdk::pubkey::Pubkey;
+use stakedex_sdk_common::BaseStakePoolAmm;
+
+struct SharedPoolState<T> {
+    pool: Arc<T>,
+    /// [`fingerprint()`] of the accounts last successfully applied by any handle
+    applied: Option<u64>,
+}
+
+/// A handle to pool state shared by multiple Amms, e.g. all pairs and the SOL <-> LST Amm
+/// of the same pool. This keeps memory and update costs proportional to the number of pools
+/// instead of Amms.
+///
+/// Every handle's [`Self::update()`] applies the accounts unless another handle has already
+/// applied the same account data, so the state is fresh as long as any of its Amms is updated.
+pub struct SharedPool<T>(Arc<RwLock<SharedPoolState<T>>>);
+
+impl<T> Clone for SharedPool<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> SharedPool<T> {
+    pub fn new(pool: T) -> Self {
+        Self(Arc::new(RwLock::new(SharedPoolState {
+            pool: Arc::new(pool),
+            applied: None,
+        })))
+    }
+
+    /// The current state. Only locks for as long as it takes to clone the [`Arc`]
+    pub fn load(&self) -> Arc<T> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pool
+            .clone()
+    }
+}
+
+impl<T: BaseStakePoolAmm + Clone> SharedPool<T> {
+    /// Updates the shared state with `account_map`, unless another handle
+    /// has already applied the same data of all the pool's accounts.
+    ///
+    /// Updates in place, only copying the state if a reader still holds it from [`Self::load()`]
+    /// so that the reader is unaffected.
+    pub fn update(&self, account_map: &AccountMap) -> Result<()> {
+        let fingerprint = fingerprint(&self.load().get_accounts_to_update(), account_map);
+        let mut state = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        if fingerprint.is_some() && state.applied == fingerprint {
+            return Ok(());
+        }
+        let res = Arc::make_mut(&mut state.pool).update(account_map);
+        state.applied = fingerprint.filter(|_| res.is_ok());
+        res
+    }
+}
+
+/// Hash of the data of `accounts` in `account_map`, `None` if any of them is missing
+fn fingerprint(accounts: &[Pubkey], account_map: &AccountMap) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    for key in accounts {
+        key.hash(&mut hasher);
+        account_map.get(key)?.data.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
@@ -1,7 +1,13 @@
-use std::sync::{atomic::AtomicU64, Arc};
+use std::sync::{atomic::AtomicU64, Arc, OnceLock};
 
-use anyhow::Result;
-use lido::state::{AccountType, Lido, Validator};
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use lido::{
+    processor::StakeType,
+    state::{AccountType, Lido, Validator},
+};
+use solana_program::pubkey::Pubkey;
+use stakedex_sdk_common::{lido_program, lido_state, RecordList};
 
 mod stakedex_traits;
 
@@ -14,37 +20,98 @@ pub const LIDO_LABEL: &str = "Lido";
 pub const LIST_HEADER_LEN: usize =
     std::mem::size_of::<u32>() + std::mem::size_of::<AccountType>() + std::mem::size_of::<u8>();
 
-#[derive(Clone, Default)]
+/// header + validators vec len as u32
+const VALIDATORS_OFFSET: usize = LIST_HEADER_LEN + 4;
+
+/// [`Validator::vote_account_address`] is the first field
+const VALIDATOR_VOTE_ACCOUNT_OFFSET: usize = 0;
+
+#[derive(Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct LidoStakedex {
     lido_state: Lido,
-    validator_list: Vec<Validator>,
+    /// Raw [`Validator`] records, parsed on read
+    validator_list: RecordList,
+    /// Not serialized, must be relinked with [`Self::set_curr_epoch()`] after deserializing
+    #[borsh(skip)]
     curr_epoch: Arc<AtomicU64>,
+    /// Not serialized, reset on [`Self::update_validator_list()`] and found on first use
+    #[borsh(skip)]
+    largest_validator_index: OnceLock<Option<usize>>,
 }
 
 impl LidoStakedex {
+    pub fn set_curr_epoch(&mut self, curr_epoch: Arc<AtomicU64>) {
+        self.curr_epoch = curr_epoch;
+    }
+
     pub fn update_lido_state(&mut self, data: &[u8]) -> Result<()> {
         self.lido_state = try_from_slice_unchecked(data)?;
         Ok(())
     }
 
+    /// Copies the validator records without parsing them.
+    ///
+    /// The largest validator is only searched for on the next withdrawal quote
     pub fn update_validator_list(&mut self, data: &[u8]) -> Result<()> {
         // first 4 bytes is len as u32
-        let len = u32::from_le_bytes(
-            data[LIST_HEADER_LEN..LIST_HEADER_LEN + 4]
-                .try_into()
-                .unwrap(),
-        ) as usize;
-        let mut validator_list = Vec::with_capacity(len);
-        let validator_iter = data[LIST_HEADER_LEN + 4..]
-            .chunks_exact(Validator::LEN)
-            .enumerate();
-        for (index, record) in validator_iter {
-            if len == index {
-                break;
-            }
-            validator_list.push(try_from_slice_unchecked::<Validator>(record)?);
-        }
-        self.validator_list = validator_list;
+        let len_data: &[u8; 4] = data
+            .get(LIST_HEADER_LEN..VALIDATORS_OFFSET)
+            .ok_or_else(|| anyhow!("Invalid validator list data"))?
+            .try_into()
+            .unwrap();
+        let len = u32::from_le_bytes(*len_data) as usize;
+        self.validator_list
+            .update(&data[VALIDATORS_OFFSET..], Validator::LEN, len)?;
+        self.largest_validator_index = OnceLock::new();
         Ok(())
     }
+
+    /// Index of the validator with the largest effective stake balance,
+    /// the only one lido allows withdrawing stake from
+    pub fn largest_validator_index(&self) -> Option<usize> {
+        *self
+            .largest_validator_index
+            .get_or_init(|| self.find_largest_validator_index())
+    }
+
+    fn find_largest_validator_index(&self) -> Option<usize> {
+        self.validators()
+            .max_by_key(|(_, v)| v.effective_stake_balance)
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the stake account of `validator` that stake is withdrawn from.
+    ///
+    /// Not cached since lido only withdraws from one validator per instruction
+    pub fn stake_account(&self, validator: &Validator) -> Pubkey {
+        validator
+            .find_stake_account_address(
+                &lido_program::ID,
+                &lido_state::ID,
+                validator.stake_seeds.begin,
+                StakeType::Stake,
+            )
+            .0
+    }
+
+    /// Parses the validator at `index`
+    pub fn validator(&self, index: usize) -> Option<Validator> {
+        try_from_slice_unchecked(self.validator_list.get(index)?).ok()
+    }
+
+    /// Parses all validators, paired with their index
+    pub fn validators(&self) -> impl Iterator<Item = (usize, Validator)> + '_ {
+        self.validator_list
+            .iter()
+            .enumerate()
+            .filter_map(|(i, record)| Some((i, try_from_slice_unchecked(record).ok()?)))
+    }
+
+    /// Parses only the validator with `vote_account_address`
+    pub fn find_validator(&self, vote_account_address: &Pubkey) -> Option<Validator> {
+        self.validator(
+            self.validator_list
+                .position_by_pubkey(VALIDATOR_VOTE_ACCOUNT_OFFSET, vote_account_address)?,
+        )
+    }
 }
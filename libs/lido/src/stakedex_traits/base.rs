@@ -3,6 +3,7 @@ use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
 use solana_program::pubkey::Pubkey;
 use stakedex_sdk_common::{
     account_missing_err, lido_program, lido_state, stsol, BaseStakePoolAmm, InitFromKeyedAccount,
+    PoolReadiness, WithdrawStakeBase,
 };
 
 use crate::{LidoStakedex, LIDO_LABEL};
@@ -55,4 +56,15 @@ impl BaseStakePoolAmm for LidoStakedex {
         self.update_validator_list(validator_list_data)?;
         Ok(())
     }
+
+    fn readiness(&self) -> PoolReadiness {
+        if self.validator_list.is_empty() {
+            PoolReadiness::MissingAuxAccounts
+        } else if !self.can_accept_stake_withdrawals() {
+            // exchange rate has not been updated for this epoch
+            PoolReadiness::AwaitingEpochUpdate
+        } else {
+            PoolReadiness::Ready
+        }
+    }
 }
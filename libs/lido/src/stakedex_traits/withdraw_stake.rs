@@ -1,6 +1,5 @@
 use anyhow::{anyhow, Result};
 use lido::{
-    processor::StakeType,
     token::{Lamports, Rational, StLamports},
     MINIMUM_STAKE_ACCOUNT_BALANCE,
 };
@@ -10,8 +9,8 @@ use solana_program::{
 };
 
 use stakedex_sdk_common::{
-    lido_program, lido_state, WithdrawStakeBase, WithdrawStakeIter, WithdrawStakeQuote,
-    STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
+    lido_program, lido_state, StakedexSdkError, WithdrawStakeBase, WithdrawStakeIter,
+    WithdrawStakeQuote, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
 };
 use stakedex_withdraw_stake_interface::{
     lido_withdraw_stake_ix, LidoWithdrawStakeKeys, LIDO_WITHDRAW_STAKE_IX_ACCOUNTS_LEN,
@@ -25,30 +24,34 @@ fn get_withdraw_stake_quote_for_validator_copied(
     lido: &LidoStakedex,
     validator_index: usize,
     withdraw_amount: u64,
-) -> Result<WithdrawStakeQuote> {
+) -> Result<WithdrawStakeQuote, StakedexSdkError> {
     let amount = StLamports(withdraw_amount);
     let validator = lido
-        .validator_list
-        .get(validator_index)
-        .ok_or_else(|| anyhow!("Validator record not found"))?;
-    // LidoError doesnt impl Error
-    let maximum_stake_validator = lido
-        .validator_list
-        .iter()
-        .max_by_key(|v| v.effective_stake_balance)
-        .ok_or_else(|| anyhow!("No active validators"))?;
-    let maximum_stake_balance = maximum_stake_validator.effective_stake_balance;
+        .validator(validator_index)
+        .ok_or(StakedexSdkError::ValidatorNotAccepted)?;
+    let largest_validator_index = lido
+        .largest_validator_index()
+        .ok_or(StakedexSdkError::ValidatorNotAccepted)?;
+    let maximum_stake_balance = if largest_validator_index == validator_index {
+        validator.effective_stake_balance
+    } else {
+        lido.validator(largest_validator_index)
+            .ok_or(StakedexSdkError::ValidatorNotAccepted)?
+            .effective_stake_balance
+    };
     if validator.effective_stake_balance == Lamports(0) {
-        return Err(anyhow!("validator has no stake"));
+        return Err(StakedexSdkError::ValidatorNotAccepted);
     }
     if validator.effective_stake_balance < maximum_stake_balance {
-        return Err(anyhow!("validator with more stake exists"));
+        // lido only allows withdrawing from the largest validator
+        return Err(StakedexSdkError::ValidatorNotAccepted);
     }
     let sol_to_withdraw = lido
         .lido_state
         .exchange_rate
         .exchange_st_sol(amount)
-        .map_err(|_| anyhow!("no stSOL minted"))?;
+        // only errs if no stSOL has been minted
+        .map_err(|_| StakedexSdkError::NoLstSupply)?;
     // TODO: this is = accounts.source_stake_account.lamports()
     // rn because there's only 1 active stake account
     // per validator, might change in the future.
@@ -61,19 +64,19 @@ fn get_withdraw_stake_quote_for_validator_copied(
         })
     .expect("Multiplying with 0.1 does not overflow or divide by zero.")
     .add(Lamports(10 * LAMPORTS_PER_SOL))
-    .map_err(|_| anyhow!("math error max_withdraw_amount"))?;
+    .map_err(|_| StakedexSdkError::MathOverflow)?;
     if sol_to_withdraw > max_withdraw_amount {
-        return Err(anyhow!("withdrawal leaves pool imbalanced"));
+        return Err(StakedexSdkError::WithdrawalLeavesPoolImbalanced);
     }
     let remaining_balance =
-        (source_balance - sol_to_withdraw).map_err(|_| anyhow!("math error remaining_balance"))?;
+        (source_balance - sol_to_withdraw).map_err(|_| StakedexSdkError::MathOverflow)?;
     if remaining_balance < MINIMUM_STAKE_ACCOUNT_BALANCE {
-        return Err(anyhow!("withdrawal leaves stake acc below min"));
+        return Err(StakedexSdkError::WithdrawalBelowMinStakeAccountBalance);
     }
 
     let lamports_out = sol_to_withdraw.0;
     if lamports_out < STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS {
-        return Err(anyhow!("withdrawal too small"));
+        return Err(StakedexSdkError::AmountTooSmall);
     }
     let lamports_staked = lamports_out - STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS;
     Ok(WithdrawStakeQuote {
@@ -100,12 +103,7 @@ impl Iterator for WithdrawStakeQuoteIter<'_> {
             return None;
         }
 
-        let (maximum_stake_validator_index, _) = self
-            .pool
-            .validator_list
-            .iter()
-            .enumerate()
-            .max_by_key(|(_, v)| v.effective_stake_balance)?;
+        let maximum_stake_validator_index = self.pool.largest_validator_index()?;
         let wsq = get_withdraw_stake_quote_for_validator_copied(
             self.pool,
             maximum_stake_validator_index,
@@ -137,22 +135,13 @@ impl WithdrawStakeBase for LidoStakedex {
 
     fn virtual_ix(&self, quote: &WithdrawStakeQuote) -> Result<Instruction> {
         let validator = self
-            .validator_list
-            .iter()
-            .find(|v| v.vote_account_address == quote.voter)
+            .find_validator(&quote.voter)
             .ok_or_else(|| anyhow!("could not find validator"))?;
         Ok(lido_withdraw_stake_ix(LidoWithdrawStakeKeys {
             lido_program: lido_program::ID,
             withdraw_stake_solido: lido_state::ID,
             withdraw_stake_stake_authority: lido_program::STAKE_AUTHORITY_ID,
-            withdraw_stake_stake_to_split: validator
-                .find_stake_account_address(
-                    &lido_program::ID,
-                    &lido_state::ID,
-                    validator.stake_seeds.begin,
-                    StakeType::Stake,
-                )
-                .0,
+            withdraw_stake_stake_to_split: self.stake_account(&validator),
             withdraw_stake_voter: quote.voter,
             withdraw_stake_validator_list: self.lido_state.validator_list,
             clock: sysvar::clock::ID,
@@ -1,3 +1,6 @@
 // struct ValidatorRecord is 53 bytes long borsh serialized
 // but marinade serializes it with 8-bytes padding so it's 61 bytes in accountinfo.data
 pub const VALIDATOR_RECORD_BYTE_LENGTH: usize = 61;
+
+// ValidatorRecord.validator_account is the first field
+pub const VALIDATOR_RECORD_ACCOUNT_OFFSET: usize = 0;
@@ -1,10 +1,12 @@
 use anyhow::{anyhow, Result};
-use borsh::BorshDeserialize;
-use consts::VALIDATOR_RECORD_BYTE_LENGTH;
+use borsh::{BorshDeserialize, BorshSerialize};
+use consts::{VALIDATOR_RECORD_ACCOUNT_OFFSET, VALIDATOR_RECORD_BYTE_LENGTH};
 use marinade_finance_interface::{
     Fee, FeeCents, LiqPool, List, StakeSystem, State, ValidatorRecord, ValidatorSystem,
 };
 use solana_program::{borsh1::try_from_slice_unchecked, pubkey::Pubkey};
+use stakedex_sdk_common::{marinade_state, PdaCache, RecordList};
+use validator_system::ValidatorRecordWrapper;
 
 mod calc;
 mod consts;
@@ -15,10 +17,15 @@ pub mod validator_system;
 
 pub const MARINADE_LABEL: &str = "Marinade";
 
-#[derive(Clone)]
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
 pub struct MarinadeStakedex {
     pub state: State,
-    pub validator_records: Vec<ValidatorRecord>,
+    /// Raw [`ValidatorRecord`]s, parsed on read
+    pub validator_records: RecordList,
+    /// Duplication flags by validator vote account.
+    /// Not serialized, populated on [`Self::update_validator_records()`]
+    #[borsh(skip)]
+    pub duplication_flags: PdaCache<Pubkey>,
 }
 
 impl Default for MarinadeStakedex {
@@ -92,7 +99,8 @@ impl Default for MarinadeStakedex {
                 stake_moved: 0,
                 max_stake_moved_per_epoch: zero_fee,
             },
-            validator_records: Vec::new(),
+            validator_records: RecordList::default(),
+            duplication_flags: PdaCache::default(),
         }
     }
 }
@@ -105,7 +113,9 @@ impl MarinadeStakedex {
         Ok(())
     }
 
-    /// data is account data of state.validator_system.validator_list.account
+    /// data is account data of state.validator_system.validator_list.account.
+    ///
+    /// Copies the validator records without parsing them
     pub fn update_validator_records(&mut self, data: &[u8]) -> Result<()> {
         // first 8 bytes are len
         let len_slice = data
@@ -115,17 +125,46 @@ impl MarinadeStakedex {
         let records_slice = data
             .get(8..)
             .ok_or_else(|| anyhow!("Could not read validator records data"))?;
-        let validator_record_iter = records_slice
-            .chunks_exact(VALIDATOR_RECORD_BYTE_LENGTH)
-            .enumerate();
-        self.validator_records.clear();
-        for (index, record) in validator_record_iter {
-            if len == index as u64 {
-                break;
-            }
-            self.validator_records
-                .push(try_from_slice_unchecked::<ValidatorRecord>(record)?);
-        }
+        self.validator_records.update(
+            records_slice,
+            VALIDATOR_RECORD_BYTE_LENGTH,
+            len.try_into()?,
+        )?;
+        self.duplication_flags.populate(
+            self.validator_records.iter().filter_map(|record| {
+                record
+                    .get(VALIDATOR_RECORD_ACCOUNT_OFFSET..VALIDATOR_RECORD_ACCOUNT_OFFSET + 32)
+                    .and_then(|key| Pubkey::try_from(key).ok())
+            }),
+            |validator_account| *validator_account,
+            find_duplication_flag,
+        );
         Ok(())
     }
+
+    /// Returns the duplication flag of `validator_account`,
+    /// from [`Self::duplication_flags`] if cached
+    pub fn duplication_flag(&self, validator_account: &Pubkey) -> Pubkey {
+        self.duplication_flags.get_or_find(validator_account, || {
+            find_duplication_flag(validator_account)
+        })
+    }
+
+    /// Parses only the record of `validator_account`
+    pub fn find_validator_record(&self, validator_account: &Pubkey) -> Option<ValidatorRecord> {
+        let index = self
+            .validator_records
+            .position_by_pubkey(VALIDATOR_RECORD_ACCOUNT_OFFSET, validator_account)?;
+        try_from_slice_unchecked(self.validator_records.get(index)?).ok()
+    }
+
+    pub fn has_validator_record(&self, validator_account: &Pubkey) -> bool {
+        self.validator_records
+            .position_by_pubkey(VALIDATOR_RECORD_ACCOUNT_OFFSET, validator_account)
+            .is_some()
+    }
+}
+
+fn find_duplication_flag(validator_account: &Pubkey) -> Pubkey {
+    ValidatorRecordWrapper::find_duplication_flag(&marinade_state::ID, validator_account).0
 }
@@ -2,8 +2,8 @@ use anyhow::Result;
 use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
 use solana_program::pubkey::Pubkey;
 use stakedex_sdk_common::{
-    account_missing_err, marinade_program, marinade_state, msol, BaseStakePoolAmm,
-    InitFromKeyedAccount,
+    account_missing_err, marinade_program, marinade_state, msol, BaseStakePoolAmm, FeeRatio,
+    InitFromKeyedAccount, PoolParam, PoolParamValue, PoolReadiness,
 };
 
 use crate::{MarinadeStakedex, MARINADE_LABEL};
@@ -61,4 +61,41 @@ impl BaseStakePoolAmm for MarinadeStakedex {
         self.update_validator_records(validator_records_data)?;
         Ok(())
     }
+
+    fn readiness(&self) -> PoolReadiness {
+        if self.validator_records.is_empty() {
+            PoolReadiness::MissingAuxAccounts
+        } else {
+            PoolReadiness::Ready
+        }
+    }
+
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        let state = &self.state;
+        Vec::from([
+            (
+                PoolParam::RewardFee,
+                PoolParamValue::Ratio(FeeRatio {
+                    num: state.reward_fee.basis_points.into(),
+                    denom: 10_000,
+                }),
+            ),
+            (
+                PoolParam::StakeWithdrawalFee,
+                PoolParamValue::Ratio(FeeRatio {
+                    num: state.withdraw_stake_account_fee.bp_cents.into(),
+                    denom: 1_000_000,
+                }),
+            ),
+            (
+                PoolParam::DepositCap,
+                PoolParamValue::Lamports(state.staking_sol_cap),
+            ),
+            (PoolParam::Paused, PoolParamValue::Bool(state.paused)),
+            (
+                PoolParam::WithdrawStakeEnabled,
+                PoolParamValue::Bool(state.withdraw_stake_account_enabled),
+            ),
+        ])
+    }
 }
@@ -3,7 +3,9 @@ use solana_program::instruction::Instruction;
 use stakedex_deposit_sol_interface::{
     marinade_deposit_sol_ix, MarinadeDepositSolKeys, MARINADE_DEPOSIT_SOL_IX_ACCOUNTS_LEN,
 };
-use stakedex_sdk_common::{marinade_program, marinade_state, DepositSol, DepositSolQuote};
+use stakedex_sdk_common::{
+    marinade_program, marinade_state, DepositSol, DepositSolQuote, StakedexSdkError,
+};
 
 use crate::{state::StateWrapper, MarinadeStakedex};
 
@@ -12,9 +14,20 @@ impl DepositSol for MarinadeStakedex {
         true
     }
 
-    fn get_deposit_sol_quote_unchecked(&self, user_lamports: u64) -> Result<DepositSolQuote> {
+    fn get_deposit_sol_quote_unchecked(
+        &self,
+        user_lamports: u64,
+    ) -> Result<DepositSolQuote, StakedexSdkError> {
         // Reference: https://github.com/marinade-finance/liquid-staking-program/blob/main/programs/marinade-finance/src/state/deposit.rs#L27
-        let out_amount = StateWrapper(&self.state).calc_msol_from_lamports(user_lamports)?;
+        let state = StateWrapper(&self.state);
+        // conservative: the part of the deposit filled by the liquidity pool's mSOL leg
+        // does not count towards the staking cap, but the liquidity pool is not accounted for
+        state
+            .check_staking_cap(user_lamports)
+            .map_err(|_| StakedexSdkError::DepositCapExceeded)?;
+        let out_amount = state
+            .calc_msol_from_lamports(user_lamports)
+            .map_err(|_| StakedexSdkError::MathOverflow)?;
         // TODO: this is a simplified calc that doesn't account for the liquidity pool, which can result in a diff of at most 1 lamport
         Ok(DepositSolQuote {
             in_amount: user_lamports,
@@ -8,7 +8,7 @@ use stakedex_sdk_common::{
     WithdrawStakeQuote,
 };
 
-use crate::{state::StateWrapper, validator_system::ValidatorRecordWrapper, MarinadeStakedex};
+use crate::{state::StateWrapper, MarinadeStakedex};
 
 impl DepositStake for MarinadeStakedex {
     fn can_accept_stake_deposits(&self) -> bool {
@@ -29,10 +29,7 @@ impl DepositStake for MarinadeStakedex {
         {
             return DepositStakeQuote::default();
         }
-        if !self
-            .validator_records
-            .iter()
-            .any(|v| v.validator_account == withdraw_stake_quote.voter)
+        if !self.has_validator_record(&withdraw_stake_quote.voter)
             && self.state.validator_system.auto_add_validator_enabled == 0
         {
             return DepositStakeQuote::default();
@@ -67,11 +64,7 @@ impl DepositStake for MarinadeStakedex {
             deposit_stake_marinade_state: marinade_state::ID,
             deposit_stake_validator_list: self.state.validator_system.validator_list.account,
             deposit_stake_stake_list: self.state.stake_system.stake_list.account,
-            deposit_stake_duplication_flag: ValidatorRecordWrapper::find_duplication_flag(
-                &marinade_state::ID,
-                &quote.voter,
-            )
-            .0,
+            deposit_stake_duplication_flag: self.duplication_flag(&quote.voter),
             deposit_stake_msol_mint_auth: marinade_program::MSOL_MINT_AUTH_ID,
             clock: sysvar::clock::ID,
             rent: sysvar::rent::ID,
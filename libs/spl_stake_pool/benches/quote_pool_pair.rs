@@ -0,0 +1,208 @@
+//! Benchmarks quoting LST -> LST pool pairs of SPL, Lido and Marinade
+//! and asserts that it does not heap-allocate.
+//!
+//! Run with `cargo bench -p stakedex_spl_stake_pool`
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use jupiter_amm_interface::{QuoteParams, SwapMode};
+use lido::{
+    state::{ExchangeRate, Lido, Validator},
+    token::{Lamports, StLamports},
+};
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use spl_stake_pool::state::{Fee, StakeStatus, ValidatorList, ValidatorStakeInfo};
+use stakedex_jup_interface::{quote_pool_pair, PrefundRepayParams};
+use stakedex_lido::{LidoStakedex, LIST_HEADER_LEN};
+use stakedex_marinade::MarinadeStakedex;
+use stakedex_sdk_common::{DepositStake, WithdrawStake};
+use stakedex_spl_stake_pool::{
+    SplStakePoolStakedex, SplStakePoolStakedexInitKeys, SplStakePoolStakedexWithWithdrawSol,
+};
+use unstake_interface::{FeeEnum, Rational};
+
+const ITERS: usize = 100_000;
+
+/// Counts calls to alloc and realloc
+struct CountingAlloc;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn main() {
+    // all pools have stake delegated to the same validators so that
+    // the withdrawn stake can be deposited
+    let voters: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+    let spl_from = spl_pool(&voters);
+    let spl_to = spl_pool(&voters);
+    let lido = lido_pool(&voters);
+    let marinade = marinade_pool();
+    let prefund_repay_params = PrefundRepayParams {
+        fee: FeeEnum::Flat {
+            ratio: Rational {
+                num: 1,
+                denom: 1000,
+            },
+        },
+        incoming_stake: 0,
+        sol_reserves_lamports: 1_000 * LAMPORTS_PER_SOL,
+        protocol_fee_dest: Pubkey::new_unique(),
+    };
+
+    bench_quote_pool_pair("spl -> spl", &prefund_repay_params, &spl_from, &spl_to);
+    bench_quote_pool_pair("lido -> spl", &prefund_repay_params, &lido, &spl_to);
+    bench_quote_pool_pair(
+        "spl -> marinade",
+        &prefund_repay_params,
+        &spl_from,
+        &marinade,
+    );
+}
+
+fn bench_quote_pool_pair<W: WithdrawStake + ?Sized, D: DepositStake + ?Sized>(
+    label: &str,
+    prefund_repay_params: &PrefundRepayParams,
+    withdraw_from: &W,
+    deposit_to: &D,
+) {
+    for swap_mode in [SwapMode::ExactIn, SwapMode::ExactOut] {
+        let quote_params = QuoteParams {
+            amount: 10 * LAMPORTS_PER_SOL,
+            input_mint: withdraw_from.staked_sol_mint(),
+            output_mint: deposit_to.staked_sol_mint(),
+            swap_mode,
+        };
+        // sanity check that the route is quotable, else we'd just be measuring the error path
+        quote_pool_pair(
+            &quote_params,
+            prefund_repay_params,
+            withdraw_from,
+            deposit_to,
+        )
+        .unwrap();
+
+        let allocs_before = ALLOCS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        for _ in 0..ITERS {
+            let _ = black_box(quote_pool_pair(
+                black_box(&quote_params),
+                prefund_repay_params,
+                withdraw_from,
+                deposit_to,
+            ));
+        }
+        let elapsed = start.elapsed();
+        let allocs = ALLOCS.load(Ordering::Relaxed) - allocs_before;
+
+        println!(
+            "quote_pool_pair {label} {swap_mode:?}: {:?}/iter, {allocs} allocs over {ITERS} iters",
+            elapsed / ITERS as u32,
+        );
+        assert_eq!(
+            allocs, 0,
+            "quote_pool_pair {label} {swap_mode:?} heap-allocated"
+        );
+    }
+}
+
+fn spl_pool(voters: &[Pubkey]) -> SplStakePoolStakedexWithWithdrawSol {
+    let mut inner = SplStakePoolStakedex::new_uninitialized(
+        SplStakePoolStakedexInitKeys {
+            stake_pool_program: spl_stake_pool::ID,
+            stake_pool_addr: Pubkey::new_unique(),
+        },
+        Default::default(),
+    );
+    let fee = Fee {
+        denominator: 1000,
+        numerator: 1,
+    };
+    let sp = &mut inner.stake_pool;
+    sp.pool_mint = Pubkey::new_unique();
+    sp.stake_deposit_authority = inner.deposit_authority_program_address;
+    sp.total_lamports = 1_100_000 * LAMPORTS_PER_SOL;
+    sp.pool_token_supply = 1_000_000 * LAMPORTS_PER_SOL;
+    sp.stake_withdrawal_fee = fee;
+    sp.stake_deposit_fee = fee;
+    sp.sol_deposit_fee = fee;
+
+    let mut validator_list = ValidatorList::new(voters.len() as u32);
+    validator_list.validators = voters
+        .iter()
+        .map(|voter| ValidatorStakeInfo {
+            active_stake_lamports: (100_000 * LAMPORTS_PER_SOL).into(),
+            status: StakeStatus::Active.into(),
+            vote_account_address: *voter,
+            ..Default::default()
+        })
+        .collect();
+    inner
+        .update_validator_list(&borsh::to_vec(&validator_list).unwrap())
+        .unwrap();
+
+    SplStakePoolStakedexWithWithdrawSol {
+        inner,
+        reserve_stake_lamports: None,
+    }
+}
+
+/// Lido only withdraws from its largest validator, which is the first of `voters`
+fn lido_pool(voters: &[Pubkey]) -> LidoStakedex {
+    let mut lido_state = Lido::default();
+    lido_state.exchange_rate = ExchangeRate {
+        computed_in_epoch: 0,
+        st_sol_supply: StLamports(1_000_000 * LAMPORTS_PER_SOL),
+        sol_balance: Lamports(1_100_000 * LAMPORTS_PER_SOL),
+    };
+    let mut validator_list_data = vec![0; LIST_HEADER_LEN];
+    validator_list_data.extend((voters.len() as u32).to_le_bytes());
+    for (i, voter) in voters.iter().enumerate() {
+        let validator = Validator {
+            vote_account_address: *voter,
+            effective_stake_balance: Lamports((200_000 - i as u64) * LAMPORTS_PER_SOL),
+            active: true,
+            ..Default::default()
+        };
+        validator_list_data.extend(borsh::to_vec(&validator).unwrap());
+    }
+
+    let mut lido = LidoStakedex::default();
+    lido.update_lido_state(&borsh::to_vec(&lido_state).unwrap())
+        .unwrap();
+    lido.update_validator_list(&validator_list_data).unwrap();
+    lido
+}
+
+/// Accepts stake from any validator
+fn marinade_pool() -> MarinadeStakedex {
+    let mut marinade = MarinadeStakedex::default();
+    let state = &mut marinade.state;
+    state.msol_supply = 1_000_000 * LAMPORTS_PER_SOL;
+    state.validator_system.total_active_balance = 1_100_000 * LAMPORTS_PER_SOL;
+    state.validator_system.auto_add_validator_enabled = 1;
+    state.staking_sol_cap = u64::MAX;
+    marinade
+}
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
@@ -16,7 +17,7 @@ const LAMPORTS_TY_DISCM: u8 = 1;
 
 const LST_ATOMICS_TY_DISCM: u8 = 2;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub enum DepositCap {
     Lamports(u64),
     LstAtomics(u64),
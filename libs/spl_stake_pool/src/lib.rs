@@ -1,23 +1,29 @@
 use std::{
     num::NonZeroU64,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{atomic::AtomicU64, Arc, OnceLock},
 };
 
 use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
 use deposit_cap_guard::{find_spl_deposit_cap_guard_state, DepositCap};
 use solana_program::{borsh1::try_from_slice_unchecked, pubkey::Pubkey};
 use spl_stake_pool::{
     error::StakePoolError,
-    find_deposit_authority_program_address, find_withdraw_authority_program_address,
-    state::{StakePool, StakeStatus, ValidatorList},
+    find_deposit_authority_program_address, find_stake_program_address,
+    find_withdraw_authority_program_address,
+    state::{StakePool, StakeStatus},
     MINIMUM_ACTIVE_STAKE,
 };
 use stakedex_sdk_common::{
-    spl_deposit_cap_guard_program, WithdrawStakeQuote, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
+    spl_deposit_cap_guard_program, PdaCache, StakedexSdkError, WithdrawStakeQuote,
+    STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
 };
 
 mod deposit_cap_guard;
 mod stakedex_traits;
+mod validator_list;
+
+pub use validator_list::*;
 
 /// vsas are required to always have a min of
 /// minimum_stake_lamports(meta, stake_program_min_delegation)
@@ -25,6 +31,21 @@ mod stakedex_traits;
 /// total lamports.
 const VSA_MIN_LAMPORTS: u64 = STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS + MINIMUM_ACTIVE_STAKE;
 
+/// Maps [`StakePoolError`] to its [`StakedexSdkError`] kind.
+///
+/// Not a `From` impl because both types are foreign to this crate.
+pub fn stake_pool_err_to_sdk_err(e: StakePoolError) -> StakedexSdkError {
+    match e {
+        StakePoolError::CalculationFailure => StakedexSdkError::MathOverflow,
+        StakePoolError::StakeListAndPoolOutOfDate => StakedexSdkError::PoolNotUpdatedThisEpoch,
+        StakePoolError::SolWithdrawalTooLarge => StakedexSdkError::InsufficientReserveLiquidity,
+        StakePoolError::InvalidSolWithdrawAuthority => StakedexSdkError::CannotAcceptSolWithdrawals,
+        StakePoolError::ValidatorNotFound => StakedexSdkError::ValidatorNotAccepted,
+        StakePoolError::WithdrawalTooSmall => StakedexSdkError::AmountTooSmall,
+        e => StakedexSdkError::Other(e.into()),
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct SplStakePoolStakedexInitKeys {
     pub stake_pool_program: Pubkey,
@@ -33,17 +54,26 @@ pub struct SplStakePoolStakedexInitKeys {
 
 /// A SPL stake pool with possibly custom program ID.
 /// Works for different deploys of spl stake pool prog - spl, sanctum spl, sanctum spl multi
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct SplStakePoolStakedex {
     pub stake_pool_addr: Pubkey,
     pub stake_pool_program: Pubkey,
     pub stake_pool_label: String,
     pub stake_pool: StakePool,
-    pub validator_list: ValidatorList,
+    pub validator_list: ValidatorListView,
+    /// Not serialized, must be relinked to the shared epoch after deserializing
+    #[borsh(skip)]
     pub curr_epoch: Arc<AtomicU64>,
     pub deposit_authority_program_address: Pubkey,
     pub spl_deposit_cap_guard_program_address: Pubkey,
     pub deposit_cap_state: Option<DepositCap>,
+    /// Not serialized, found on [`Self::update_stake_pool()`] or first use
+    #[borsh(skip)]
+    withdraw_authority_addr: OnceLock<Pubkey>,
+    /// Validator stake accounts by vote account.
+    /// Not serialized, populated on [`Self::update_validator_list()`]
+    #[borsh(skip)]
+    pub validator_stake_accounts: PdaCache<Pubkey>,
 }
 
 impl SplStakePoolStakedex {
@@ -70,11 +100,26 @@ impl SplStakePoolStakedex {
 
     pub fn update_stake_pool(&mut self, data: &[u8]) -> Result<()> {
         self.stake_pool = try_from_slice_unchecked::<StakePool>(data)?;
+        // cache it so that building ixs doesn't have to
+        self.withdraw_authority_addr();
         Ok(())
     }
 
+    /// Copies the validators without parsing them, see [`ValidatorListView`]
     pub fn update_validator_list(&mut self, data: &[u8]) -> Result<()> {
-        self.validator_list = try_from_slice_unchecked::<ValidatorList>(data)?;
+        self.validator_list.update(data)?;
+        let (stake_pool_program, stake_pool_addr) = (self.stake_pool_program, self.stake_pool_addr);
+        self.validator_stake_accounts.populate(
+            self.validator_list.iter(),
+            |v| v.vote_account_address,
+            |v| {
+                find_validator_stake_account(
+                    &stake_pool_program,
+                    &stake_pool_addr,
+                    &v.vote_account_address,
+                )
+            },
+        );
         Ok(())
     }
 
@@ -97,10 +142,21 @@ impl SplStakePoolStakedex {
             >= self.curr_epoch.load(std::sync::atomic::Ordering::Relaxed)
     }
 
-    /// Computes and returns the stake withdraw authority PDA
+    /// Returns the stake withdraw authority PDA, computed once and cached.
     /// Assumes all included pools are permissionless, i.e. using the default withdraw authority
     pub fn withdraw_authority_addr(&self) -> Pubkey {
-        find_withdraw_authority_program_address(&self.stake_pool_program, &self.stake_pool_addr).0
+        *self.withdraw_authority_addr.get_or_init(|| {
+            find_withdraw_authority_program_address(&self.stake_pool_program, &self.stake_pool_addr)
+                .0
+        })
+    }
+
+    /// Returns the validator stake account of `voter`,
+    /// from [`Self::validator_stake_accounts`] if cached
+    pub fn validator_stake_account(&self, voter: &Pubkey) -> Pubkey {
+        self.validator_stake_accounts.get_or_find(voter, || {
+            find_validator_stake_account(&self.stake_pool_program, &self.stake_pool_addr, voter)
+        })
     }
 
     fn get_withdraw_stake_quote_for_validator_copied(
@@ -110,7 +166,6 @@ impl SplStakePoolStakedex {
     ) -> Result<WithdrawStakeQuote, StakePoolError> {
         let validator_list_entry = self
             .validator_list
-            .validators
             .get(validator_index)
             .ok_or(StakePoolError::ValidatorNotFound)?;
         // only handle withdrawal from active stake accounts for simplicity.
@@ -172,7 +227,15 @@ impl SplStakePoolStakedex {
 /// Newtype encapsulating [`SplStakePoolStakedex`] because
 /// DepositSol, DepositStake, WithdrawStake does not require fetching reserve stake account
 /// for quoting, only WithdrawSol does.
-#[derive(Debug, Clone, Default)]
+fn find_validator_stake_account(
+    stake_pool_program: &Pubkey,
+    stake_pool_addr: &Pubkey,
+    voter: &Pubkey,
+) -> Pubkey {
+    find_stake_program_address(stake_pool_program, voter, stake_pool_addr, None).0
+}
+
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct SplStakePoolStakedexWithWithdrawSol {
     pub inner: SplStakePoolStakedex,
     // NonZero: reserve should always have at least rent-exempt lamports.
@@ -182,7 +245,9 @@ pub struct SplStakePoolStakedexWithWithdrawSol {
 
 #[cfg(test)]
 mod tests {
-    use stakedex_jup_interface::DepositSolWrapper;
+    use spl_stake_pool::state::{Fee, ValidatorList, ValidatorStakeInfo};
+    use stakedex_jup_interface::{DepositSolWrapper, SharedPool};
+    use stakedex_sdk_common::{diff_pool_params, BaseStakePoolAmm, PoolParam};
 
     use crate::*;
 
@@ -190,6 +255,102 @@ mod tests {
     fn test_wrapper_impls_amm_correctly_compile_time() {
         // DepositSolWrapper<SplStakePoolDepositSol>
         // impls Amm
-        let _sp = DepositSolWrapper(SplStakePoolStakedex::default());
+        let _sp = DepositSolWrapper(SharedPool::new(SplStakePoolStakedex::default()));
+    }
+
+    #[test]
+    fn test_params_diff_only_changed_params() {
+        let old = SplStakePoolStakedex::default();
+        let mut new = old.clone();
+        new.stake_pool.sol_deposit_fee = Fee {
+            denominator: 1000,
+            numerator: 1,
+        };
+        new.stake_pool.preferred_deposit_validator_vote_address = Some(Pubkey::new_unique());
+        // not a param
+        new.stake_pool.total_lamports = 1;
+
+        let (old_params, new_params) = (old.params(), new.params());
+        let changed: Vec<PoolParam> = diff_pool_params(&old_params, &new_params)
+            .map(|(param, _, _)| param)
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                PoolParam::SolDepositFee,
+                PoolParam::PreferredDepositValidator
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_validator_list_matches_borsh() {
+        let mut validator_list = ValidatorList::new(3);
+        validator_list.validators = (0..2u64)
+            .map(|i| ValidatorStakeInfo {
+                active_stake_lamports: (i + 1).into(),
+                vote_account_address: Pubkey::new_unique(),
+                ..Default::default()
+            })
+            .collect();
+        let mut data = borsh::to_vec(&validator_list).unwrap();
+        // unused capacity for the 3rd validator
+        data.resize(data.len() + std::mem::size_of::<ValidatorStakeInfo>(), 0);
+
+        let mut sp = SplStakePoolStakedex::default();
+        sp.update_validator_list(&data).unwrap();
+        assert_eq!(sp.validator_list.header, validator_list.header);
+        assert!(sp
+            .validator_list
+            .iter()
+            .eq(validator_list.validators.iter()));
+        for v in validator_list.validators.iter() {
+            assert_eq!(sp.validator_list.find(&v.vote_account_address), Some(v));
+        }
+        assert_eq!(sp.validator_list.find(&Pubkey::new_unique()), None);
+        assert_eq!(
+            borsh::to_vec(&sp.validator_list).unwrap(),
+            borsh::to_vec(&validator_list).unwrap()
+        );
+        assert!(sp.update_validator_list(&data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_validator_stake_accounts_populated_on_update() {
+        let mut validator_list = ValidatorList::new(2);
+        validator_list.validators = (0..2)
+            .map(|_| ValidatorStakeInfo {
+                vote_account_address: Pubkey::new_unique(),
+                ..Default::default()
+            })
+            .collect();
+        let data = borsh::to_vec(&validator_list).unwrap();
+
+        let mut sp = SplStakePoolStakedex::default();
+        sp.update_validator_list(&data).unwrap();
+        assert_eq!(sp.validator_stake_accounts.len(), 2);
+        for v in validator_list.validators.iter() {
+            let voter = v.vote_account_address;
+            assert_eq!(
+                sp.validator_stake_accounts.get(&voter),
+                Some(
+                    find_stake_program_address(
+                        &sp.stake_pool_program,
+                        &voter,
+                        &sp.stake_pool_addr,
+                        None
+                    )
+                    .0
+                )
+            );
+        }
+
+        // removed validators are evicted, without modifying the cache of older copies
+        let before = sp.clone();
+        validator_list.validators.pop();
+        sp.update_validator_list(&borsh::to_vec(&validator_list).unwrap())
+            .unwrap();
+        assert_eq!(sp.validator_stake_accounts.len(), 1);
+        assert_eq!(before.validator_stake_accounts.len(), 2);
     }
 }
@@ -3,10 +3,32 @@ use std::num::NonZeroU64;
 use anyhow::Result;
 use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
 use solana_program::pubkey::Pubkey;
-use spl_stake_pool::error::StakePoolError;
-use stakedex_sdk_common::{account_missing_err, BaseStakePoolAmm, InitFromKeyedAccount};
+use spl_stake_pool::{
+    error::StakePoolError,
+    state::{AccountType, Fee},
+};
+use stakedex_sdk_common::{
+    account_missing_err, BaseStakePoolAmm, FeeRatio, InitFromKeyedAccount, PoolParam,
+    PoolParamValue, PoolReadiness,
+};
+
+use crate::{
+    deposit_cap_guard::DepositCap, SplStakePoolStakedex, SplStakePoolStakedexWithWithdrawSol,
+};
+
+fn fee_param(fee: &Fee) -> PoolParamValue {
+    PoolParamValue::Ratio(FeeRatio {
+        num: fee.numerator,
+        denom: fee.denominator,
+    })
+}
 
-use crate::{SplStakePoolStakedex, SplStakePoolStakedexWithWithdrawSol};
+fn referral_fee_param(pct: u8) -> PoolParamValue {
+    PoolParamValue::Ratio(FeeRatio {
+        num: pct.into(),
+        denom: 100,
+    })
+}
 
 impl InitFromKeyedAccount for SplStakePoolStakedex {
     /// Initialize from stake pool main account
@@ -94,6 +116,74 @@ impl BaseStakePoolAmm for SplStakePoolStakedex {
         }
         Ok(())
     }
+
+    fn readiness(&self) -> PoolReadiness {
+        let is_deposit_capped = self.is_sol_deposit_capped() || self.is_stake_deposit_capped();
+        if self.validator_list.header.account_type == AccountType::Uninitialized
+            || (is_deposit_capped && self.deposit_cap_state.is_none())
+        {
+            PoolReadiness::MissingAuxAccounts
+        } else if !self.is_updated_this_epoch() {
+            PoolReadiness::AwaitingEpochUpdate
+        } else {
+            PoolReadiness::Ready
+        }
+    }
+
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        let sp = &self.stake_pool;
+        let deposit_cap = if self.is_sol_deposit_capped() || self.is_stake_deposit_capped() {
+            match self.deposit_cap_state {
+                Some(DepositCap::Lamports(lamports)) => PoolParamValue::Lamports(lamports),
+                Some(DepositCap::LstAtomics(atomics)) => PoolParamValue::LstAtomics(atomics),
+                None => PoolParamValue::None,
+            }
+        } else {
+            PoolParamValue::None
+        };
+        Vec::from([
+            (PoolParam::StakeDepositFee, fee_param(&sp.stake_deposit_fee)),
+            (PoolParam::SolDepositFee, fee_param(&sp.sol_deposit_fee)),
+            (
+                PoolParam::StakeWithdrawalFee,
+                fee_param(&sp.stake_withdrawal_fee),
+            ),
+            (
+                PoolParam::SolWithdrawalFee,
+                fee_param(&sp.sol_withdrawal_fee),
+            ),
+            (
+                PoolParam::StakeReferralFee,
+                referral_fee_param(sp.stake_referral_fee),
+            ),
+            (
+                PoolParam::SolReferralFee,
+                referral_fee_param(sp.sol_referral_fee),
+            ),
+            (PoolParam::RewardFee, fee_param(&sp.epoch_fee)),
+            (
+                PoolParam::StakeDepositAuthority,
+                PoolParamValue::Pubkey(sp.stake_deposit_authority),
+            ),
+            (
+                PoolParam::SolDepositAuthority,
+                sp.sol_deposit_authority.into(),
+            ),
+            (
+                PoolParam::SolWithdrawAuthority,
+                sp.sol_withdraw_authority.into(),
+            ),
+            (
+                PoolParam::PreferredDepositValidator,
+                sp.preferred_deposit_validator_vote_address.into(),
+            ),
+            (
+                PoolParam::PreferredWithdrawValidator,
+                sp.preferred_withdraw_validator_vote_address.into(),
+            ),
+            (PoolParam::DepositCap, deposit_cap),
+        ])
+    }
 }
 
 impl InitFromKeyedAccount for SplStakePoolStakedexWithWithdrawSol {
@@ -144,4 +234,18 @@ impl BaseStakePoolAmm for SplStakePoolStakedexWithWithdrawSol {
         self.reserve_stake_lamports = Some(reserve_stake_lamports);
         Ok(())
     }
+
+    fn readiness(&self) -> PoolReadiness {
+        match self.inner.readiness() {
+            PoolReadiness::Ready if self.reserve_stake_lamports.is_none() => {
+                PoolReadiness::MissingAuxAccounts
+            }
+            r => r,
+        }
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        self.inner.params()
+    }
 }
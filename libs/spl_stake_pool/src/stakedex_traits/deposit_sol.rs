@@ -1,11 +1,10 @@
 use anyhow::{anyhow, Result};
 use solana_program::instruction::Instruction;
-use spl_stake_pool::error::StakePoolError;
 use stakedex_deposit_sol_interface::{
     spl_stake_pool_deposit_sol_ix, SplStakePoolDepositSolKeys,
     SPL_STAKE_POOL_DEPOSIT_SOL_IX_ACCOUNTS_LEN,
 };
-use stakedex_sdk_common::{DepositSol, DepositSolQuote};
+use stakedex_sdk_common::{DepositSol, DepositSolQuote, StakedexSdkError};
 
 use crate::{
     deposit_cap_guard::{to_deposit_cap_guard_ix, DepositCap},
@@ -19,7 +18,10 @@ impl DepositSol for SplStakePoolStakedexWithWithdrawSol {
     }
 
     #[inline]
-    fn get_deposit_sol_quote_unchecked(&self, lamports: u64) -> Result<DepositSolQuote> {
+    fn get_deposit_sol_quote_unchecked(
+        &self,
+        lamports: u64,
+    ) -> Result<DepositSolQuote, StakedexSdkError> {
         self.inner.get_deposit_sol_quote_unchecked(lamports)
     }
 
@@ -42,12 +44,15 @@ impl DepositSol for SplStakePoolStakedex {
         self.is_updated_this_epoch()
     }
 
-    fn get_deposit_sol_quote_unchecked(&self, lamports: u64) -> Result<DepositSolQuote> {
+    fn get_deposit_sol_quote_unchecked(
+        &self,
+        lamports: u64,
+    ) -> Result<DepositSolQuote, StakedexSdkError> {
         // Reference: https://github.com/solana-labs/solana-program-library/blob/56cdef9ee82877622a074aa74560742264f20591/stake-pool/program/src/processor.rs#L2268
         let new_pool_tokens = self
             .stake_pool
             .calc_pool_tokens_for_deposit(lamports)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         if self.is_sol_deposit_capped() {
             let deposit_cap = self
                 .deposit_cap_state
@@ -67,27 +72,27 @@ impl DepositSol for SplStakePoolStakedex {
                 }
             };
             if will_exceed_deposit_cap {
-                return Err(anyhow!("deposit will exceed cap"));
+                return Err(StakedexSdkError::DepositCapExceeded);
             }
         }
         let pool_tokens_sol_deposit_fee = self
             .stake_pool
             .calc_pool_tokens_sol_deposit_fee(new_pool_tokens)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         let pool_tokens_user = new_pool_tokens
             .checked_sub(pool_tokens_sol_deposit_fee)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         let pool_tokens_referral_fee = self
             .stake_pool
             .calc_pool_tokens_sol_referral_fee(pool_tokens_sol_deposit_fee)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         // since we set referrer to the receiving fee_token_acc, referral fee is effectively kicked back to user
         let out_amount = pool_tokens_user
             .checked_add(pool_tokens_referral_fee)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         let fee_amount = pool_tokens_sol_deposit_fee
             .checked_sub(pool_tokens_referral_fee)
-            .ok_or(StakePoolError::CalculationFailure)?;
+            .ok_or(StakedexSdkError::MathOverflow)?;
         Ok(DepositSolQuote {
             in_amount: lamports,
             out_amount,
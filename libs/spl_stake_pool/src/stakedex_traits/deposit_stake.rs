@@ -1,6 +1,6 @@
 use anyhow::Result;
 use solana_program::{instruction::Instruction, stake, sysvar};
-use spl_stake_pool::{find_stake_program_address, state::StakeStatus};
+use spl_stake_pool::state::StakeStatus;
 use stakedex_deposit_stake_interface::{
     spl_stake_pool_deposit_stake_ix, SplStakePoolDepositStakeKeys,
     SPL_STAKE_POOL_DEPOSIT_STAKE_IX_ACCOUNTS_LEN,
@@ -150,13 +150,7 @@ impl DepositStake for SplStakePoolStakedex {
         quote: &DepositStakeQuote,
         _deposit_stake_info: &DepositStakeInfo,
     ) -> Result<Instruction> {
-        let deposit_stake_validator_stake = find_stake_program_address(
-            &self.stake_pool_program,
-            &quote.voter,
-            &self.stake_pool_addr,
-            None,
-        )
-        .0;
+        let deposit_stake_validator_stake = self.validator_stake_account(&quote.voter);
         // spl_stake_pool_deposit_stake_ix works for all spl-stake-pool like
         // (spl, sanctum-spl, sanctum-spl-multi) because the accounts interface is the exact same
         let ix = spl_stake_pool_deposit_stake_ix(SplStakePoolDepositStakeKeys {
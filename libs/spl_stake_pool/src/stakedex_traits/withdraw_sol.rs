@@ -1,13 +1,15 @@
 use anyhow::Result;
 use solana_program::{instruction::Instruction, pubkey::Pubkey, stake, sysvar};
 use spl_stake_pool::{error::StakePoolError, state::StakePool, MINIMUM_RESERVE_LAMPORTS};
-use stakedex_sdk_common::{WithdrawSol, WithdrawSolQuote, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS};
+use stakedex_sdk_common::{
+    StakedexSdkError, WithdrawSol, WithdrawSolQuote, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
+};
 use stakedex_withdraw_sol_interface::{
     spl_stake_pool_withdraw_sol_ix, SplStakePoolWithdrawSolKeys,
     SPL_STAKE_POOL_WITHDRAW_SOL_IX_ACCOUNTS_LEN,
 };
 
-use crate::SplStakePoolStakedexWithWithdrawSol;
+use crate::{stake_pool_err_to_sdk_err, SplStakePoolStakedexWithWithdrawSol};
 
 // Adapted from: https://github.com/solana-labs/solana-program-library/blob/17a228bb8e36737209ca5d5375415c70da37c311/stake-pool/program/src/lib.rs#L80-L84
 // Will have to change if network changes rent-exempt parameters
@@ -15,24 +17,25 @@ const TOTAL_MIN_RESERVE_LAMPORTS: u64 =
     STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS + MINIMUM_RESERVE_LAMPORTS;
 
 impl WithdrawSol for SplStakePoolStakedexWithWithdrawSol {
-    fn get_withdraw_sol_quote(&self, lst: u64) -> Result<WithdrawSolQuote> {
+    fn get_withdraw_sol_quote(&self, lst: u64) -> Result<WithdrawSolQuote, StakedexSdkError> {
         // Interface does not work for pools with permissioned SOL withdrawals
         if self.inner.stake_pool.sol_withdraw_authority.is_some() {
-            return Err(StakePoolError::InvalidSolWithdrawAuthority.into());
+            return Err(StakedexSdkError::CannotAcceptSolWithdrawals);
         }
         if !self.inner.is_updated_this_epoch() {
-            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+            return Err(StakedexSdkError::PoolNotUpdatedThisEpoch);
         }
-        let quote = get_withdraw_sol_quote_copied(&self.inner.stake_pool, lst)?;
+        let quote = get_withdraw_sol_quote_copied(&self.inner.stake_pool, lst)
+            .map_err(stake_pool_err_to_sdk_err)?;
         let curr_reserve_lamports: u64 = self
             .reserve_stake_lamports
-            .ok_or(StakePoolError::WrongStakeStake)?
+            .ok_or_else(|| stake_pool_err_to_sdk_err(StakePoolError::WrongStakeStake))?
             .into();
         // Adapted from:
         // https://github.com/solana-labs/solana-program-library/blob/17a228bb8e36737209ca5d5375415c70da37c311/stake-pool/program/src/processor.rs#L3102-L3116
         let new_reserve_lamports = curr_reserve_lamports.saturating_sub(quote.out_amount);
         if new_reserve_lamports < TOTAL_MIN_RESERVE_LAMPORTS {
-            return Err(StakePoolError::SolWithdrawalTooLarge.into());
+            return Err(StakedexSdkError::InsufficientReserveLiquidity);
         }
         Ok(quote)
     }
@@ -68,7 +71,10 @@ impl WithdrawSol for SplStakePoolStakedexWithWithdrawSol {
 
 // Assumes
 // - manager fee account is a valid token account (fees will be 0 otherwise)
-fn get_withdraw_sol_quote_copied(sp: &StakePool, pool_tokens: u64) -> Result<WithdrawSolQuote> {
+fn get_withdraw_sol_quote_copied(
+    sp: &StakePool,
+    pool_tokens: u64,
+) -> Result<WithdrawSolQuote, StakePoolError> {
     // Copied from
     // https://github.com/solana-labs/solana-program-library/blob/17a228bb8e36737209ca5d5375415c70da37c311/stake-pool/program/src/processor.rs#L3066-L3094
     let pool_tokens_fee = sp
@@ -81,7 +87,7 @@ fn get_withdraw_sol_quote_copied(sp: &StakePool, pool_tokens: u64) -> Result<Wit
         .calc_lamports_withdraw_amount(pool_tokens_burnt)
         .ok_or(StakePoolError::CalculationFailure)?;
     if withdraw_lamports == 0 {
-        return Err(StakePoolError::WithdrawalTooSmall.into());
+        return Err(StakePoolError::WithdrawalTooSmall);
     }
 
     // estimate pool_tokens_fee in terms of SOL instead of LST
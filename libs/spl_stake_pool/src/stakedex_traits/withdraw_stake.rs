@@ -2,7 +2,6 @@ use std::sync::atomic::Ordering;
 
 use anyhow::Result;
 use solana_program::{instruction::Instruction, pubkey::Pubkey, stake, system_program, sysvar};
-use spl_stake_pool::find_stake_program_address;
 use stakedex_sdk_common::{WithdrawStakeBase, WithdrawStakeIter, WithdrawStakeQuote};
 use stakedex_withdraw_stake_interface::{
     spl_stake_pool_withdraw_stake_ix, SplStakePoolWithdrawStakeKeys,
@@ -32,12 +31,11 @@ impl WithdrawStakeQuoteIter<'_> {
             .pool
             .get_withdraw_stake_quote_for_validator_copied(curr_index, self.withdraw_amount)
             .unwrap_or_default();
-        let next_state =
-            if curr_index >= self.pool.validator_list.validators.len().checked_sub(1)? {
-                WithdrawStakeQuoteIterState::Ended
-            } else {
-                WithdrawStakeQuoteIterState::Normal(curr_index.checked_add(1)?)
-            };
+        let next_state = if curr_index >= self.pool.validator_list.len().checked_sub(1)? {
+            WithdrawStakeQuoteIterState::Ended
+        } else {
+            WithdrawStakeQuoteIterState::Normal(curr_index.checked_add(1)?)
+        };
         Some((wsq, next_state))
     }
 
@@ -48,13 +46,8 @@ impl WithdrawStakeQuoteIter<'_> {
             .stake_pool
             .preferred_withdraw_validator_vote_address
             .unwrap();
-        let (preferred_index, vsi) = self
-            .pool
-            .validator_list
-            .validators
-            .iter()
-            .enumerate()
-            .find(|(_, vsi)| vsi.vote_account_address == preferred_voter)?;
+        let preferred_index = self.pool.validator_list.position(&preferred_voter)?;
+        let vsi = self.pool.validator_list.get(preferred_index)?;
         // check if preferred can service withdrawals,
         // falling back to normal if preferred does not have enough to service withdrawals
         let lamports_per_pool_token = self.pool.stake_pool.get_lamports_per_pool_token()?;
@@ -113,13 +106,7 @@ impl WithdrawStakeBase for SplStakePoolStakedex {
     }
 
     fn virtual_ix(&self, quote: &WithdrawStakeQuote) -> Result<Instruction> {
-        let withdraw_stake_stake_to_split = find_stake_program_address(
-            &self.stake_pool_program,
-            &quote.voter,
-            &self.stake_pool_addr,
-            None,
-        )
-        .0;
+        let withdraw_stake_stake_to_split = self.validator_stake_account(&quote.voter);
         // spl_stake_pool_withdraw_stake_ix works for all spl-stake-pool like
         // (spl, sanctum-spl, sanctum-spl-multi) because the accounts interface is the exact same
         Ok(spl_stake_pool_withdraw_stake_ix(
@@ -0,0 +1,103 @@
+use std::{
+    io::{Read, Write},
+    mem::{offset_of, size_of},
+};
+
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{borsh1::try_from_slice_unchecked, pubkey::Pubkey};
+use spl_stake_pool::state::{ValidatorListHeader, ValidatorStakeInfo};
+use stakedex_sdk_common::RecordList;
+
+/// account_type + max_validators
+const VALIDATOR_LIST_HEADER_LEN: usize = 1 + 4;
+
+/// header + validators vec len as u32
+const VALIDATORS_OFFSET: usize = VALIDATOR_LIST_HEADER_LEN + 4;
+
+const VALIDATOR_STAKE_INFO_LEN: usize = size_of::<ValidatorStakeInfo>();
+
+const VALIDATOR_VOTE_ACCOUNT_OFFSET: usize = offset_of!(ValidatorStakeInfo, vote_account_address);
+
+/// A stake pool's validator list, with the [`ValidatorStakeInfo`]s kept as raw bytes.
+///
+/// Since [`ValidatorStakeInfo`] is [`bytemuck::Pod`] with an alignment of 1,
+/// updating it is a memcpy and validators are read in place.
+/// Serialized the same as [`spl_stake_pool::state::ValidatorList`].
+#[derive(Clone, Debug, Default)]
+pub struct ValidatorListView {
+    pub header: ValidatorListHeader,
+    validators: RecordList,
+}
+
+impl ValidatorListView {
+    /// Copies the validators from a validator list account's data, excluding unused capacity
+    pub fn update(&mut self, data: &[u8]) -> Result<()> {
+        let header_data = data
+            .get(..VALIDATOR_LIST_HEADER_LEN)
+            .ok_or_else(|| anyhow!("Invalid validator list data"))?;
+        let len_data: &[u8; 4] = data
+            .get(VALIDATOR_LIST_HEADER_LEN..VALIDATORS_OFFSET)
+            .ok_or_else(|| anyhow!("Invalid validator list data"))?
+            .try_into()
+            .unwrap();
+        let len = u32::from_le_bytes(*len_data) as usize;
+        self.header = try_from_slice_unchecked(header_data)?;
+        self.validators
+            .update(&data[VALIDATORS_OFFSET..], VALIDATOR_STAKE_INFO_LEN, len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.validators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ValidatorStakeInfo> {
+        bytemuck::try_from_bytes(self.validators.get(index)?).ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ValidatorStakeInfo> + Clone {
+        self.validators
+            .iter()
+            .filter_map(|record| bytemuck::try_from_bytes(record).ok())
+    }
+
+    /// Index of the validator with `vote_account_address`, without casting any records
+    pub fn position(&self, vote_account_address: &Pubkey) -> Option<usize> {
+        self.validators
+            .position_by_pubkey(VALIDATOR_VOTE_ACCOUNT_OFFSET, vote_account_address)
+    }
+
+    pub fn find(&self, vote_account_address: &Pubkey) -> Option<&ValidatorStakeInfo> {
+        self.get(self.position(vote_account_address)?)
+    }
+}
+
+impl BorshSerialize for ValidatorListView {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.header.serialize(writer)?;
+        // len always fits since it was read from a u32
+        (self.len() as u32).serialize(writer)?;
+        writer.write_all(self.validators.as_bytes())
+    }
+}
+
+impl BorshDeserialize for ValidatorListView {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let header = ValidatorListHeader::deserialize_reader(reader)?;
+        let len = u32::deserialize_reader(reader)? as usize;
+        // read no more than is available instead of allocating a possibly corrupt len upfront
+        let mut data = Vec::new();
+        reader
+            .take(len as u64 * VALIDATOR_STAKE_INFO_LEN as u64)
+            .read_to_end(&mut data)?;
+        let mut validators = RecordList::default();
+        validators
+            .update(&data, VALIDATOR_STAKE_INFO_LEN, len)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::UnexpectedEof, e.to_string()))?;
+        Ok(Self { header, validators })
+    }
+}
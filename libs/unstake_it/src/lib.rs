@@ -1,4 +1,5 @@
 use anyhow::Result;
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{borsh1::try_from_slice_unchecked, pubkey::Pubkey};
 use unstake_interface::{Fee, FeeEnum, Pool, ProtocolFee, Rational};
 
@@ -13,7 +14,7 @@ pub const UNSTAKE_IT_LABEL: &str = "Unstake.it";
 
 pub const ZERO_RATIONAL: Rational = Rational { num: 0, denom: 1 };
 
-#[derive(Clone)]
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
 pub struct UnstakeItStakedex {
     pub pool: Pool,
     pub fee: Fee,
@@ -64,5 +65,5 @@ impl UnstakeItStakedex {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct UnstakeItStakedexPrefund(pub UnstakeItStakedex);
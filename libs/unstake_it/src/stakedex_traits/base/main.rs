@@ -2,12 +2,20 @@ use anyhow::Result;
 use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
 use solana_program::pubkey::Pubkey;
 use stakedex_sdk_common::{
-    account_missing_err, unstake_it_pool, unstake_it_program, BaseStakePoolAmm,
-    InitFromKeyedAccount,
+    account_missing_err, unstake_it_pool, unstake_it_program, BaseStakePoolAmm, FeeRatio,
+    InitFromKeyedAccount, PoolParam, PoolParamValue, PoolReadiness,
 };
+use unstake_interface::{FeeEnum, Rational};
 
 use crate::{UnstakeItStakedex, UNSTAKE_IT_LABEL};
 
+fn fee_ratio(Rational { num, denom }: &Rational) -> FeeRatio {
+    FeeRatio {
+        num: *num,
+        denom: *denom,
+    }
+}
+
 impl InitFromKeyedAccount for UnstakeItStakedex {
     /// Not usable until first update()
     #[inline]
@@ -75,4 +83,38 @@ impl BaseStakePoolAmm for UnstakeItStakedex {
             .lamports;
         Ok(())
     }
+
+    fn readiness(&self) -> PoolReadiness {
+        // not usable until first update()
+        if self.pool.lp_mint == Pubkey::default() {
+            PoolReadiness::MissingAuxAccounts
+        } else {
+            PoolReadiness::Ready
+        }
+    }
+
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        let unstake_fee = match &self.fee.fee {
+            FeeEnum::Flat { ratio } => PoolParamValue::Ratio(fee_ratio(ratio)),
+            FeeEnum::LiquidityLinear { params } => PoolParamValue::LiquidityLinear {
+                max_liq_remaining: fee_ratio(&params.max_liq_remaining),
+                zero_liq_remaining: fee_ratio(&params.zero_liq_remaining),
+            },
+        };
+        Vec::from([
+            (PoolParam::UnstakeFee, unstake_fee),
+            (
+                PoolParam::ProtocolFee,
+                PoolParamValue::Ratio(fee_ratio(&self.protocol_fee.fee_ratio)),
+            ),
+            (
+                PoolParam::ReferrerFee,
+                PoolParamValue::Ratio(fee_ratio(&self.protocol_fee.referrer_fee_ratio)),
+            ),
+            (
+                PoolParam::ProtocolFeeDestination,
+                PoolParamValue::Pubkey(self.protocol_fee.destination),
+            ),
+        ])
+    }
 }
@@ -1,6 +1,6 @@
 use jupiter_amm_interface::AccountMap;
 use solana_program::pubkey::Pubkey;
-use stakedex_sdk_common::BaseStakePoolAmm;
+use stakedex_sdk_common::{BaseStakePoolAmm, PoolParam, PoolParamValue, PoolReadiness};
 
 use crate::UnstakeItStakedexPrefund;
 
@@ -34,4 +34,14 @@ impl BaseStakePoolAmm for UnstakeItStakedexPrefund {
     fn update(&mut self, account_map: &AccountMap) -> anyhow::Result<()> {
         self.0.update(account_map)
     }
+
+    #[inline]
+    fn readiness(&self) -> PoolReadiness {
+        self.0.readiness()
+    }
+
+    #[inline]
+    fn params(&self) -> Vec<(PoolParam, PoolParamValue)> {
+        self.0.params()
+    }
 }
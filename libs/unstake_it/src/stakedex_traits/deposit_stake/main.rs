@@ -5,10 +5,11 @@ use stakedex_deposit_stake_interface::{
     UNSTAKE_IT_DEPOSIT_STAKE_IX_ACCOUNTS_LEN,
 };
 use stakedex_sdk_common::{
-    unstake_it_pool, unstake_it_program, DepositStake, DepositStakeInfo, DepositStakeQuote,
+    reverse_deposit_stake_quote, unstake_it_pool, unstake_it_program, DepositStake,
+    DepositStakeInfo, DepositStakeQuote, DepositStakeQuoteErr, StakedexSdkError,
     WithdrawStakeQuote,
 };
-use unstake_lib::PoolBalance;
+use unstake_lib::{PoolBalance, ReverseFeeArgs, UnstakeFeeCalc};
 
 use crate::{find_stake_account_record, quote_deposit_stake, UnstakeItStakedex};
 
@@ -31,6 +32,30 @@ impl DepositStake for UnstakeItStakedex {
         )
     }
 
+    fn get_deposit_stake_quote_exact_out(
+        &self,
+        out_amount: u64,
+        voter: Pubkey,
+    ) -> Result<(WithdrawStakeQuote, DepositStakeQuote), StakedexSdkError> {
+        if !self.can_accept_stake_deposits() {
+            return Err(DepositStakeQuoteErr::CannotAcceptStakeDeposits.into());
+        }
+        // pseudo_reverse() is not exact due to precision losses,
+        // so only use it as the starting point of the search
+        let initial_lamports = self
+            .fee
+            .fee
+            .pseudo_reverse(ReverseFeeArgs {
+                pool_balance: PoolBalance {
+                    pool_incoming_stake: self.pool.incoming_stake,
+                    sol_reserves_lamports: self.sol_reserves_lamports,
+                },
+                lamports_after_fee: out_amount,
+            })
+            .unwrap_or(out_amount);
+        reverse_deposit_stake_quote(self, out_amount, voter, initial_lamports)
+    }
+
     fn virtual_ix(
         &self,
         _quote: &DepositStakeQuote,
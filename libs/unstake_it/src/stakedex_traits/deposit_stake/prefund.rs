@@ -1,5 +1,5 @@
 use solana_program::instruction::Instruction;
-use stakedex_jup_interface::{PrefundRepayParams, PREFUND_FLASH_LOAN_LAMPORTS};
+use stakedex_jup_interface::{slumdog_target_lamports, PREFUND_FLASH_LOAN_LAMPORTS};
 use stakedex_sdk_common::{DepositStake, DepositStakeInfo, DepositStakeQuote, WithdrawStakeQuote};
 use unstake_lib::{PoolBalance, RationalQty};
 
@@ -16,13 +16,13 @@ impl DepositStake for UnstakeItStakedexPrefund {
     ) -> DepositStakeQuote {
         // modify pool_incoming_stake and sol_reserves_lamports
         // according to the prefund unstake
-        let params = PrefundRepayParams {
-            fee: self.0.fee.fee.clone(),
-            incoming_stake: self.0.pool.incoming_stake,
-            sol_reserves_lamports: self.0.sol_reserves_lamports,
-            protocol_fee_dest: self.0.protocol_fee.destination,
-        };
-        let slumdog_target_lamports = match params.slumdog_target_lamports() {
+        let slumdog_target_lamports = match slumdog_target_lamports(
+            &self.0.fee.fee,
+            PoolBalance {
+                pool_incoming_stake: self.0.pool.incoming_stake,
+                sol_reserves_lamports: self.0.sol_reserves_lamports,
+            },
+        ) {
             Ok(s) => s,
             Err(_) => return DepositStakeQuote::default(),
         };
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use jupiter_amm_interface::{AccountMap, AmmContext};
+use sanctum_lst_list::{PoolInfo, SanctumLst};
+use solana_sdk::pubkey::Pubkey;
+use stakedex_sdk_common::{lido_state, marinade_state, unstake_it_pool};
+
+use crate::{Stakedex, UpdateReport, SANCTUM_LST_LIST};
+
+/// The stake pool program an SPL stake pool in the sanctum LST list is deployed under
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SplPoolProgram {
+    Spl,
+    SanctumSpl,
+    SanctumSplMulti,
+}
+
+impl SplPoolProgram {
+    /// `None` if `pool` is not an SPL stake pool
+    pub fn of(pool: &PoolInfo) -> Option<Self> {
+        match pool {
+            PoolInfo::Spl(_) => Some(Self::Spl),
+            PoolInfo::SanctumSpl(_) => Some(Self::SanctumSpl),
+            PoolInfo::SanctumSplMulti(_) => Some(Self::SanctumSplMulti),
+            PoolInfo::Lido | PoolInfo::Marinade | PoolInfo::ReservePool | PoolInfo::SPool(..) => {
+                None
+            }
+        }
+    }
+}
+
+/// Initializes a [`Stakedex`] with only some of its pools, so that only their accounts
+/// are fetched, stored and updated.
+///
+/// By default, this loads the same pools as [`Stakedex::init_accounts()`]
+/// and [`Stakedex::from_fetched_accounts()`] with the embedded sanctum LST list.
+///
+/// SPL stake pool filters are AND-ed together, and each filter matches any of its values.
+///
+/// Excluded unstake.it, Marinade or Lido pools are left in their default state
+/// and skipped like quarantined pools, see [`Stakedex::is_excluded()`].
+/// Without unstake.it, PrefundSwapViaStake and SOL DepositStake routes are unavailable.
+#[derive(Clone)]
+pub struct StakedexBuilder<'a> {
+    /// Whether to consider the embedded sanctum LST list in addition to `sanctum_lsts`
+    with_sanctum_lst_list: bool,
+    sanctum_lsts: Vec<&'a SanctumLst>,
+    unstakeit: bool,
+    marinade: bool,
+    lido: bool,
+    spl_mints: Option<HashSet<Pubkey>>,
+    spl_programs: Option<HashSet<SplPoolProgram>>,
+    spl_labels: Option<HashSet<String>>,
+}
+
+impl Default for StakedexBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> StakedexBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            with_sanctum_lst_list: true,
+            sanctum_lsts: Vec::new(),
+            unstakeit: true,
+            marinade: true,
+            lido: true,
+            spl_mints: None,
+            spl_programs: None,
+            spl_labels: None,
+        }
+    }
+
+    /// Does not load the embedded sanctum LST list,
+    /// so that only the SPL stake pools of [`Self::with_sanctum_lsts()`] are considered
+    pub fn without_sanctum_lst_list(mut self) -> Self {
+        self.with_sanctum_lst_list = false;
+        self
+    }
+
+    /// Adds `sanctum_lsts` to the LSTs whose SPL stake pools are considered,
+    /// e.g. ones not in the embedded sanctum LST list yet
+    pub fn with_sanctum_lsts(
+        mut self,
+        sanctum_lsts: impl IntoIterator<Item = &'a SanctumLst>,
+    ) -> Self {
+        self.sanctum_lsts.extend(sanctum_lsts);
+        self
+    }
+
+    pub fn with_unstakeit(mut self, enabled: bool) -> Self {
+        self.unstakeit = enabled;
+        self
+    }
+
+    pub fn with_marinade(mut self, enabled: bool) -> Self {
+        self.marinade = enabled;
+        self
+    }
+
+    pub fn with_lido(mut self, enabled: bool) -> Self {
+        self.lido = enabled;
+        self
+    }
+
+    /// Only loads the SPL stake pools of `mints`
+    pub fn with_spl_mints(mut self, mints: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.spl_mints
+            .get_or_insert_with(HashSet::new)
+            .extend(mints);
+        self
+    }
+
+    /// Only loads the SPL stake pools deployed under `programs`
+    pub fn with_spl_programs(mut self, programs: impl IntoIterator<Item = SplPoolProgram>) -> Self {
+        self.spl_programs
+            .get_or_insert_with(HashSet::new)
+            .extend(programs);
+        self
+    }
+
+    /// Only loads the SPL stake pools whose LST symbol or name is one of `labels`, e.g. "jitoSOL"
+    pub fn with_spl_labels(mut self, labels: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.spl_labels
+            .get_or_insert_with(HashSet::new)
+            .extend(labels.into_iter().map(Into::into));
+        self
+    }
+
+    /// The LSTs whose SPL stake pools pass all filters.
+    ///
+    /// Each mint is only yielded once, preferring the embedded sanctum LST list's entry
+    pub fn spl_lsts(&self) -> impl Iterator<Item = &'a SanctumLst> + '_ {
+        let embedded: &'static [SanctumLst] = if self.with_sanctum_lst_list {
+            &SANCTUM_LST_LIST.sanctum_lst_list
+        } else {
+            &[]
+        };
+        let mut seen_mints = HashSet::new();
+        embedded
+            .iter()
+            .chain(self.sanctum_lsts.iter().copied())
+            .filter(|lst| self.includes_spl(lst))
+            .filter(move |lst| seen_mints.insert(lst.mint))
+    }
+
+    fn includes_spl(&self, lst: &SanctumLst) -> bool {
+        let Some(program) = SplPoolProgram::of(&lst.pool) else {
+            return false;
+        };
+        self.spl_mints
+            .as_ref()
+            .map_or(true, |mints| mints.contains(&lst.mint))
+            && self
+                .spl_programs
+                .as_ref()
+                .map_or(true, |programs| programs.contains(&program))
+            && self.spl_labels.as_ref().map_or(true, |labels| {
+                labels.contains(&lst.symbol) || labels.contains(&lst.name)
+            })
+    }
+
+    /// main_state_keys of the excluded unstake.it, Marinade and Lido pools
+    fn excluded(&self) -> HashSet<Pubkey> {
+        [
+            (self.unstakeit, unstake_it_pool::ID),
+            (self.marinade, marinade_state::ID),
+            (self.lido, lido_state::ID),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| !enabled)
+        .map(|(_, main_state_key)| main_state_key)
+        .collect()
+    }
+
+    /// Same as [`Stakedex::init_accounts()`], but only the accounts of the included pools
+    pub fn init_accounts(&self) -> Vec<Pubkey> {
+        Stakedex::init_accounts_excluding(self.spl_lsts(), &self.excluded())
+    }
+
+    /// Same as [`Stakedex::from_fetched_accounts()`] with the accounts of [`Self::init_accounts()`]
+    pub fn build(
+        &self,
+        accounts: &AccountMap,
+        amm_context: &AmmContext,
+    ) -> (Stakedex, Vec<anyhow::Error>) {
+        let (stakedex, report) = self.build_with_report(accounts, amm_context);
+        (stakedex, report.into_errs())
+    }
+
+    /// Same as [`Self::build()`], but reports the outcome of each included pool's initialization,
+    /// see [`Stakedex::from_fetched_accounts_with_report()`]
+    pub fn build_with_report(
+        &self,
+        accounts: &AccountMap,
+        amm_context: &AmmContext,
+    ) -> (Stakedex, UpdateReport) {
+        Stakedex::from_fetched_accounts_excluding(
+            self.spl_lsts(),
+            self.excluded(),
+            accounts,
+            amm_context,
+        )
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use arc_swap::ArcSwap;
+use jupiter_amm_interface::AccountMap;
+
+use crate::{AccountsUpdate, Stakedex, UpdateReport};
+
+/// A [`Stakedex`] shared between many readers and a single updater.
+///
+/// Readers [`Self::load()`] an immutable snapshot without locking and quote against it
+/// for as long as they hold it, unaffected by concurrent updates.
+/// Updates are applied to a private copy that shares unchanged pools with
+/// the published snapshot, and are published by atomically swapping the snapshot.
+///
+/// Each epoch's snapshots share a `curr_epoch` that is never modified once published:
+/// the updater links itself and its pools to a new one when the epoch changes, see
+/// [`Stakedex::update_clock()`], so readers never observe an epoch their pools were not published with.
+/// Amms created from a snapshot, e.g. with [`Stakedex::amms_for_mints()`], keep its epoch.
+pub struct ConcurrentStakedex {
+    published: ArcSwap<Stakedex>,
+    /// Holds the account cache and index needed by [`Stakedex::update_accounts()`],
+    /// which snapshots do not.
+    writer: Mutex<Stakedex>,
+}
+
+impl ConcurrentStakedex {
+    pub fn new(mut stakedex: Stakedex) -> Self {
+        // detach from the epoch passed to its pools, e.g. jupiter's ClockRef,
+        // which could otherwise be modified while published
+        stakedex.relink_curr_epoch(stakedex.curr_epoch());
+        stakedex.relink_epoch_on_change = true;
+        Self {
+            published: ArcSwap::from_pointee(snapshot_of(&stakedex)),
+            writer: Mutex::new(stakedex),
+        }
+    }
+
+    /// The current snapshot. Cheap and lock-free
+    pub fn load(&self) -> Arc<Stakedex> {
+        self.published.load_full()
+    }
+
+    /// [`Stakedex::update_with_report()`] then publishes a new snapshot.
+    ///
+    /// Copies every pool since all of them are updated,
+    /// use [`Self::update_accounts()`] to only copy the pools whose accounts changed.
+    pub fn update(&self, account_map: &AccountMap) -> UpdateReport {
+        self.modify(|stakedex| stakedex.update_with_report(account_map))
+    }
+
+    /// [`Stakedex::update_accounts()`] then publishes a new snapshot.
+    ///
+    /// Only the pools that read any of the accounts in `changed` are copied,
+    /// e.g. refreshing an SPL stake pool's validator list does not copy Marinade's or Lido's state.
+    pub fn update_accounts(&self, changed: &AccountMap) -> AccountsUpdate {
+        self.modify(|stakedex| stakedex.update_accounts(changed))
+    }
+
+    /// Applies `f` to the updater's copy, e.g. to add or remove pools, then publishes a new snapshot.
+    ///
+    /// Calls are serialized so that concurrent modifications are not lost.
+    pub fn modify<R>(&self, f: impl FnOnce(&mut Stakedex) -> R) -> R {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let res = f(&mut writer);
+        self.published.store(Arc::new(snapshot_of(&writer)));
+        res
+    }
+}
+
+/// Clones `stakedex` without the account cache and index or the param change listener,
+/// which readers do not need.
+/// Pools are shared with `stakedex` until either copy updates them.
+fn snapshot_of(stakedex: &Stakedex) -> Stakedex {
+    Stakedex {
+        spls: stakedex.spls.clone(),
+        unstakeit: stakedex.unstakeit.clone(),
+        marinade: stakedex.marinade.clone(),
+        lido: stakedex.lido.clone(),
+        spl_mint_index: stakedex.spl_mint_index.clone(),
+        spl_main_state_index: stakedex.spl_main_state_index.clone(),
+        curr_epoch: stakedex.curr_epoch.clone(),
+        quarantined: stakedex.quarantined.clone(),
+        quarantine_failing_pools: stakedex.quarantine_failing_pools,
+        excluded: stakedex.excluded.clone(),
+        account_index: Default::default(),
+        account_cache: Default::default(),
+        param_change_listener: None,
+        // so that updating a clone of the snapshot does not modify the published epoch
+        relink_epoch_on_change: true,
+    }
+}
@@ -0,0 +1,93 @@
+use jupiter_amm_interface::{QuoteParams, SwapMode};
+use solana_sdk::pubkey::Pubkey;
+use stakedex_sdk_common::StakedexSdkError;
+
+use crate::{RouteKind, RouteQuote, Stakedex};
+
+/// Upper bound of [`Stakedex::quote_max_in()`]'s search. Far above the total SOL supply,
+/// while leaving headroom so that amounts + fees in the pools' quoting code do not overflow.
+pub const MAX_IN_SEARCH_LIMIT: u64 = u64::MAX / 4;
+
+impl Stakedex {
+    /// Finds the largest input amount that `kind` can still quote a nonzero output for,
+    /// returning the quote for that amount. Its `quote.in_amount` is the max tradeable size.
+    ///
+    /// Since this searches over the pools' actual quoting logic, all of their constraints apply,
+    /// e.g. SPL reserve minimums and deposit caps, validator stake limits, Marinade's staking cap,
+    /// Lido's withdrawal limits and unstake.it's SOL reserves.
+    ///
+    /// Assumes the amounts that can be quoted form a single contiguous range.
+    /// Returns the quote for [`MAX_IN_SEARCH_LIMIT`] if the route is effectively unlimited.
+    /// Errs with the quote err of the smallest amount tried if no amount can be quoted.
+    pub fn quote_max_in(
+        &self,
+        kind: RouteKind,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+    ) -> Result<RouteQuote, StakedexSdkError> {
+        let quote_nonzero = |amount: u64| -> Result<RouteQuote, StakedexSdkError> {
+            let rq = self.quote_route(
+                kind,
+                &QuoteParams {
+                    amount,
+                    input_mint: *input_mint,
+                    output_mint: *output_mint,
+                    swap_mode: SwapMode::ExactIn,
+                },
+            )?;
+            if rq.quote.out_amount == 0 {
+                return Err(StakedexSdkError::AmountTooSmall);
+            }
+            Ok(rq)
+        };
+
+        // 1. find the smallest power of 2 that can be quoted to skip min amount constraints
+        let mut first_err = None;
+        let mut lo = None;
+        for shift in 0..u64::BITS {
+            let amount = 1 << shift;
+            if amount > MAX_IN_SEARCH_LIMIT {
+                break;
+            }
+            match quote_nonzero(amount) {
+                Ok(rq) => {
+                    lo = Some(rq);
+                    break;
+                }
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        let mut lo = match lo {
+            Some(rq) => rq,
+            None => return Err(first_err.unwrap_or(StakedexSdkError::AmountTooSmall)),
+        };
+
+        // 2. double until it can no longer be quoted
+        let mut hi = loop {
+            let next = lo
+                .quote
+                .in_amount
+                .saturating_mul(2)
+                .min(MAX_IN_SEARCH_LIMIT);
+            if next == lo.quote.in_amount {
+                return Ok(lo);
+            }
+            match quote_nonzero(next) {
+                Ok(rq) => lo = rq,
+                Err(_) => break next,
+            }
+        };
+
+        // 3. binary search for the boundary in (lo, hi)
+        while hi - lo.quote.in_amount > 1 {
+            let mid = lo.quote.in_amount + (hi - lo.quote.in_amount) / 2;
+            match quote_nonzero(mid) {
+                Ok(rq) => lo = rq,
+                Err(_) => hi = mid,
+            }
+        }
+        Ok(lo)
+    }
+}
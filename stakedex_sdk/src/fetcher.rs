@@ -0,0 +1,401 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{PoisonError, RwLock},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use jupiter_amm_interface::{AccountMap, AmmContext, ClockRef};
+use sanctum_lst_list::SanctumLst;
+use solana_sdk::{account::Account, clock::Clock, pubkey::Pubkey, sysvar};
+use stakedex_sdk_common::StakedexSdkError;
+use tokio::time::MissedTickBehavior;
+
+use crate::{Stakedex, StakedexBuilder, UpdateReport};
+
+/// Max number of accounts per `getMultipleAccounts` RPC call
+pub const RPC_MAX_ACCOUNTS_PER_BATCH: usize = 100;
+
+/// Max number of [`Stakedex::update()`] rounds [`StakedexRefresher::bootstrap()`] performs
+/// to resolve accounts that depend on other accounts' data, e.g. validator lists.
+pub const MAX_BOOTSTRAP_ROUNDS: usize = 4;
+
+/// Source of on-chain accounts to initialize and update [`Stakedex`] with
+pub trait AccountFetcher {
+    /// Max number of pubkeys [`Self::fetch_batch()`] accepts at once
+    fn max_batch_size(&self) -> usize {
+        RPC_MAX_ACCOUNTS_PER_BATCH
+    }
+
+    /// Fetches `pubkeys`, returning `None` for accounts that do not exist,
+    /// in the same order as `pubkeys`. `pubkeys.len()` is at most [`Self::max_batch_size()`].
+    fn fetch_batch(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> impl Future<Output = Result<Vec<Option<Account>>, StakedexSdkError>> + Send;
+}
+
+impl AccountFetcher for solana_client::nonblocking::rpc_client::RpcClient {
+    async fn fetch_batch(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, StakedexSdkError> {
+        self.get_multiple_accounts(pubkeys)
+            .await
+            .map_err(|e| StakedexSdkError::Other(e.into()))
+    }
+}
+
+/// How [`fetch_accounts()`] retries failed batches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Number of retries after the first attempt of each batch
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled for each subsequent retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Fetches `pubkeys` in batches of [`AccountFetcher::max_batch_size()`], retrying failed batches.
+///
+/// Duplicate pubkeys are only fetched once. Accounts that do not exist are omitted from the result.
+/// Errs with the last batch err if a batch still fails after all retries.
+pub async fn fetch_accounts<F: AccountFetcher + ?Sized>(
+    fetcher: &F,
+    pubkeys: &[Pubkey],
+    retry: &RetryConfig,
+) -> Result<AccountMap, StakedexSdkError> {
+    let mut seen = HashSet::new();
+    let unique: Vec<Pubkey> = pubkeys
+        .iter()
+        .filter(|pk| seen.insert(**pk))
+        .copied()
+        .collect();
+    let mut res = AccountMap::default();
+    for batch in unique.chunks(fetcher.max_batch_size().max(1)) {
+        let fetched = fetch_batch_with_retries(fetcher, batch, retry).await?;
+        if fetched.len() != batch.len() {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "fetcher returned {} accounts for {} pubkeys",
+                fetched.len(),
+                batch.len()
+            )));
+        }
+        res.extend(
+            batch
+                .iter()
+                .zip(fetched)
+                .filter_map(|(pk, acc)| acc.map(|acc| (*pk, acc))),
+        );
+    }
+    Ok(res)
+}
+
+async fn fetch_batch_with_retries<F: AccountFetcher + ?Sized>(
+    fetcher: &F,
+    batch: &[Pubkey],
+    retry: &RetryConfig,
+) -> Result<Vec<Option<Account>>, StakedexSdkError> {
+    let mut backoff = retry.backoff;
+    let mut retries = 0;
+    loop {
+        match fetcher.fetch_batch(batch).await {
+            Ok(fetched) => return Ok(fetched),
+            Err(e) if retries >= retry.max_retries => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+                retries += 1;
+            }
+        }
+    }
+}
+
+/// An [`AccountFetcher`] backed by an in-memory account map,
+/// for tests and replaying previously fetched accounts without a network
+#[derive(Debug)]
+pub struct InMemoryAccountFetcher {
+    accounts: RwLock<AccountMap>,
+    max_batch_size: usize,
+}
+
+impl Default for InMemoryAccountFetcher {
+    fn default() -> Self {
+        Self::new(AccountMap::default())
+    }
+}
+
+impl InMemoryAccountFetcher {
+    pub fn new(accounts: AccountMap) -> Self {
+        Self {
+            accounts: RwLock::new(accounts),
+            max_batch_size: RPC_MAX_ACCOUNTS_PER_BATCH,
+        }
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Inserts or replaces an account, returning the replaced account
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) -> Option<Account> {
+        self.accounts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(pubkey, account)
+    }
+
+    pub fn remove_account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.accounts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(pubkey)
+    }
+}
+
+impl AccountFetcher for InMemoryAccountFetcher {
+    fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    async fn fetch_batch(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, StakedexSdkError> {
+        if pubkeys.len() > self.max_batch_size {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "batch of {} exceeds max batch size {}",
+                pubkeys.len(),
+                self.max_batch_size
+            )));
+        }
+        let accounts = self.accounts.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(pubkeys.iter().map(|pk| accounts.get(pk).cloned()).collect())
+    }
+}
+
+/// Keeps a [`Stakedex`] up to date by fetching its accounts from an [`AccountFetcher`]
+pub struct StakedexRefresher<F> {
+    fetcher: F,
+    stakedex: Stakedex,
+    retry: RetryConfig,
+}
+
+impl<F: AccountFetcher> StakedexRefresher<F> {
+    /// Initializes a [`Stakedex`] from `sanctum_lsts` and updates it until all accounts
+    /// that depend on other accounts' data (validator lists, reserves, deposit cap states)
+    /// have been fetched, up to [`MAX_BOOTSTRAP_ROUNDS`] times.
+    ///
+    /// Returns the report of the last update. Only errs if fetching fails,
+    /// pools that fail to initialize or update are left in the report.
+    pub async fn bootstrap(
+        fetcher: F,
+        sanctum_lsts: &[SanctumLst],
+        retry: RetryConfig,
+    ) -> Result<(Self, UpdateReport), StakedexSdkError> {
+        let builder = StakedexBuilder::new()
+            .without_sanctum_lst_list()
+            .with_sanctum_lsts(sanctum_lsts);
+        Self::bootstrap_with_builder(fetcher, &builder, retry).await
+    }
+
+    /// Same as [`Self::bootstrap()`], but only fetches the accounts of the pools included by `builder`
+    pub async fn bootstrap_with_builder(
+        fetcher: F,
+        builder: &StakedexBuilder<'_>,
+        retry: RetryConfig,
+    ) -> Result<(Self, UpdateReport), StakedexSdkError> {
+        let mut init_keys = builder.init_accounts();
+        init_keys.push(sysvar::clock::ID);
+        let accounts = fetch_accounts(&fetcher, &init_keys, &retry).await?;
+        let clock: Clock = bincode::deserialize(
+            &accounts
+                .get(&sysvar::clock::ID)
+                .ok_or_else(|| anyhow!("clock sysvar missing"))?
+                .data,
+        )
+        .map_err(|e| StakedexSdkError::Other(e.into()))?;
+        let amm_context = AmmContext {
+            clock_ref: ClockRef::from(clock),
+        };
+        let (stakedex, init_report) = builder.build_with_report(&accounts, &amm_context);
+        let mut refresher = Self {
+            fetcher,
+            stakedex,
+            retry,
+        };
+
+        let mut report = UpdateReport::default();
+        let mut fetched: HashSet<Pubkey> = HashSet::new();
+        for _ in 0..MAX_BOOTSTRAP_ROUNDS {
+            let keys = refresher.stakedex.get_accounts_to_update();
+            if keys.iter().all(|k| fetched.contains(k)) {
+                break;
+            }
+            fetched.extend(keys.iter().copied());
+            report = refresher.refresh().await?;
+        }
+        // keep SPL stake pools that failed to initialize since update() doesn't know about them
+        let UpdateReport {
+            pools: init_pools,
+            errs: init_errs,
+        } = init_report;
+        report.errs.splice(0..0, init_errs);
+        report
+            .pools
+            .extend(init_pools.into_iter().filter(|p| p.readiness.is_none()));
+        Ok((refresher, report))
+    }
+
+    pub fn new(fetcher: F, stakedex: Stakedex, retry: RetryConfig) -> Self {
+        Self {
+            fetcher,
+            stakedex,
+            retry,
+        }
+    }
+
+    pub fn stakedex(&self) -> &Stakedex {
+        &self.stakedex
+    }
+
+    /// For adding or removing pools, which are picked up by the next refresh
+    pub fn stakedex_mut(&mut self) -> &mut Stakedex {
+        &mut self.stakedex
+    }
+
+    pub fn fetcher(&self) -> &F {
+        &self.fetcher
+    }
+
+    pub fn into_inner(self) -> (F, Stakedex) {
+        (self.fetcher, self.stakedex)
+    }
+
+    /// Fetches [`Stakedex::get_accounts_to_update()`] and updates with them once
+    pub async fn refresh(&mut self) -> Result<UpdateReport, StakedexSdkError> {
+        let keys = self.stakedex.get_accounts_to_update();
+        let accounts = fetch_accounts(&self.fetcher, &keys, &self.retry).await?;
+        Ok(self.stakedex.update_with_report(&accounts))
+    }
+
+    /// Refreshes every `period` forever, passing the result of each refresh to `on_refresh`.
+    ///
+    /// Drop the returned future to stop refreshing.
+    pub async fn run(
+        &mut self,
+        period: Duration,
+        mut on_refresh: impl FnMut(&Stakedex, Result<UpdateReport, StakedexSdkError>),
+    ) {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let res = self.refresh().await;
+            on_refresh(&self.stakedex, res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn account_with_lamports(lamports: u64) -> Account {
+        Account {
+            lamports,
+            ..Default::default()
+        }
+    }
+
+    fn in_memory_fetcher(n: usize) -> (Vec<Pubkey>, InMemoryAccountFetcher) {
+        let pubkeys: Vec<Pubkey> = (0..n).map(|_| Pubkey::new_unique()).collect();
+        let accounts = pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pk)| (*pk, account_with_lamports(i as u64)))
+            .collect();
+        (pubkeys, InMemoryAccountFetcher::new(accounts))
+    }
+
+    const NO_BACKOFF: RetryConfig = RetryConfig {
+        max_retries: 2,
+        backoff: Duration::ZERO,
+    };
+
+    #[tokio::test]
+    async fn fetch_accounts_batches_above_max_batch_size() {
+        let (pubkeys, fetcher) = in_memory_fetcher(250);
+        let fetched = fetch_accounts(&fetcher, &pubkeys, &NO_BACKOFF)
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 250);
+        for (i, pk) in pubkeys.iter().enumerate() {
+            assert_eq!(fetched[pk].lamports, i as u64);
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_accounts_omits_missing_and_dedups() {
+        let (mut pubkeys, fetcher) = in_memory_fetcher(3);
+        fetcher.remove_account(&pubkeys[1]);
+        pubkeys.push(pubkeys[0]);
+        let fetched = fetch_accounts(&fetcher, &pubkeys, &NO_BACKOFF)
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 2);
+        assert!(!fetched.contains_key(&pubkeys[1]));
+    }
+
+    struct FlakyFetcher {
+        inner: InMemoryAccountFetcher,
+        failures_left: AtomicUsize,
+    }
+
+    impl AccountFetcher for FlakyFetcher {
+        async fn fetch_batch(
+            &self,
+            pubkeys: &[Pubkey],
+        ) -> Result<Vec<Option<Account>>, StakedexSdkError> {
+            if self
+                .failures_left
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(StakedexSdkError::Other(anyhow!("rate limited")));
+            }
+            self.inner.fetch_batch(pubkeys).await
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_accounts_retries() {
+        let (pubkeys, inner) = in_memory_fetcher(3);
+        let fetcher = FlakyFetcher {
+            inner,
+            failures_left: AtomicUsize::new(2),
+        };
+        let fetched = fetch_accounts(&fetcher, &pubkeys, &NO_BACKOFF)
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 3);
+
+        fetcher.failures_left.store(3, Ordering::Relaxed);
+        assert!(fetch_accounts(&fetcher, &pubkeys, &NO_BACKOFF)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use itertools::Itertools;
+use jupiter_amm_interface::AccountMap;
+use solana_sdk::pubkey::Pubkey;
+use stakedex_sdk_common::BaseStakePoolAmm;
+
+use crate::{update_report::Quarantine, Stakedex, UpdateReport};
+
+/// Outcome of [`Stakedex::update_accounts()`]
+#[derive(Debug, Default)]
+pub struct AccountsUpdate {
+    /// Reports of only the pools that read any of the changed accounts
+    pub report: UpdateReport,
+
+    /// Mints of the updated pools, both before and after the update
+    pub changed_mints: HashSet<Pubkey>,
+
+    /// Whether quotes of all routes may have changed, i.e. the epoch changed
+    /// or unstake.it, which prefunds all PrefundSwapViaStake routes, was updated
+    pub all_changed: bool,
+}
+
+impl AccountsUpdate {
+    /// main_state_keys of the updated pools
+    pub fn changed_pools(&self) -> impl Iterator<Item = &Pubkey> {
+        self.report.pools.iter().map(|p| &p.main_state_key)
+    }
+
+    /// Whether quotes for `input_mint -> output_mint` may have changed
+    pub fn is_mint_pair_changed(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> bool {
+        self.all_changed
+            || self.changed_mints.contains(input_mint)
+            || self.changed_mints.contains(output_mint)
+    }
+}
+
+impl Stakedex {
+    /// Updates only the pools that read any of the accounts in `changed`,
+    /// e.g. accounts received from a websocket or geyser subscription.
+    ///
+    /// The pools' other accounts are taken from the accounts last passed to
+    /// [`Self::update()`] or this fn, so [`Self::update()`] must have been called
+    /// with all of [`Self::get_accounts_to_update()`] at least once before.
+    pub fn update_accounts(&mut self, changed: &AccountMap) -> AccountsUpdate {
+        let mut res = AccountsUpdate::default();
+        match self.update_clock_from_accounts(changed) {
+            Ok(epoch_changed) => res.all_changed = epoch_changed,
+            Err(e) => res.report.errs.push(e),
+        }
+
+        let affected: Vec<Pubkey> = changed
+            .keys()
+            .filter_map(|k| self.account_index.get(k))
+            .flatten()
+            .copied()
+            .unique()
+            .collect();
+        self.cache_accounts(changed);
+
+        let account_cache = std::mem::take(&mut self.account_cache);
+        let mut quarantine = Quarantine::take(self);
+        for main_state_key in affected {
+            let pool = match self.pool_mut_by_main_state_key(&main_state_key) {
+                Some(p) => p,
+                None => continue,
+            };
+            res.changed_mints.insert(pool.staked_sol_mint());
+            res.report
+                .pools
+                .push(quarantine.update_pool(pool, &account_cache));
+            res.changed_mints.insert(pool.staked_sol_mint());
+        }
+        quarantine.restore(self);
+        self.account_cache = account_cache;
+
+        if self
+            .unstakeit
+            .get_accounts_to_update()
+            .iter()
+            .any(|k| changed.contains_key(k))
+        {
+            res.all_changed = true;
+        }
+        // stake pool account could've been reinitialized with a different mint
+        self.rebuild_spl_indices();
+        res
+    }
+
+    /// Only copies the matching pool if it is shared with a clone of `self`
+    fn pool_mut_by_main_state_key(
+        &mut self,
+        main_state_key: &Pubkey,
+    ) -> Option<&mut dyn BaseStakePoolAmm> {
+        if let Some(i) = self.spl_main_state_index.get(main_state_key).copied() {
+            return self
+                .spls
+                .get_mut(i)
+                .map(|spl| Arc::make_mut(spl) as &mut dyn BaseStakePoolAmm);
+        }
+        if self.is_excluded(main_state_key) {
+            None
+        } else if *main_state_key == self.unstakeit.main_state_key() {
+            Some(Arc::make_mut(&mut self.unstakeit) as &mut dyn BaseStakePoolAmm)
+        } else if *main_state_key == self.marinade.main_state_key() {
+            Some(Arc::make_mut(&mut self.marinade) as &mut dyn BaseStakePoolAmm)
+        } else if *main_state_key == self.lido.main_state_key() {
+            Some(Arc::make_mut(&mut self.lido) as &mut dyn BaseStakePoolAmm)
+        } else {
+            None
+        }
+    }
+
+    /// Also drops cached accounts that no pool reads anymore
+    pub(crate) fn rebuild_account_index(&mut self) {
+        let account_index =
+            self.all_pools()
+                .fold(HashMap::<Pubkey, Vec<Pubkey>>::new(), |mut index, p| {
+                    let main_state_key = p.main_state_key();
+                    for account in p.get_accounts_to_update() {
+                        index.entry(account).or_default().push(main_state_key);
+                    }
+                    index
+                });
+        self.account_index = account_index;
+        self.account_cache
+            .retain(|k, _| self.account_index.contains_key(k));
+    }
+
+    /// Caches the accounts in `account_map` that are read by any pool
+    pub(crate) fn cache_accounts(&mut self, account_map: &AccountMap) {
+        self.account_cache.extend(
+            account_map
+                .iter()
+                .filter(|(k, _)| self.account_index.contains_key(*k))
+                .map(|(k, acc)| (*k, acc.clone())),
+        );
+    }
+}
@@ -1,13 +1,24 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use anyhow::{anyhow, Result};
-use itertools::Itertools;
 use jupiter_amm_interface::{
-    AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams, SwapParams,
+    AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, Quote, QuoteParams, SwapMode, SwapParams,
 };
 use lazy_static::lazy_static;
 use sanctum_lst_list::{PoolInfo, SanctumLst};
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program};
+use solana_sdk::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
 use spl_token::native_mint;
 use stakedex_interface::{
     DepositStakeKeys, PrefundSwapViaStakeIxArgs, PrefundSwapViaStakeKeys,
@@ -15,23 +26,58 @@ use stakedex_interface::{
     StakeWrappedSolKeys, SwapViaStakeArgs, WithdrawWrappedSolIxArgs, WithdrawWrappedSolKeys,
 };
 use stakedex_jup_interface::{
-    manual_concat_get_account_metas, prefund_get_account_metas, quote_pool_pair, DepositSolWrapper,
-    DepositWithdrawSolWrapper, OneWayPoolPair, PrefundRepayParams, TwoWayPoolPair,
+    manual_concat_get_account_metas, prefund_get_account_metas,
+    quote_pool_pair_detailed_for_voters, PoolPairQuote, PrefundRepayParams,
 };
 use stakedex_lido::LidoStakedex;
 use stakedex_marinade::MarinadeStakedex;
 use stakedex_sdk_common::{
-    find_fee_token_acc, lido_state, marinade_state, msol,
+    fee_token_acc, lido_state, marinade_state, msol,
     stakedex_program::{self, WSOL_FEE_TOKEN_ACCOUNT_ID},
-    stsol, unstake_it_program, wsol, wsol_bridge_in, BaseStakePoolAmm, DepositSol, DepositStake,
-    DepositStakeInfo, DepositStakeQuote, InitFromKeyedAccount, WithdrawSol, WithdrawStake,
-    WithdrawStakeQuote, DEPOSIT_STAKE_DST_TOKEN_ACCOUNT_INDEX,
+    stsol, unstake_it_pool, unstake_it_program, wsol, wsol_bridge_in, BaseStakePoolAmm, DepositSol,
+    DepositStake, DepositStakeInfo, DepositStakeQuote, InitFromKeyedAccount, PoolReadiness,
+    StakedexSdkError, WithdrawSol, WithdrawStake, WithdrawStakeQuote,
+    DEPOSIT_STAKE_DST_TOKEN_ACCOUNT_INDEX,
 };
 use stakedex_spl_stake_pool::{SplStakePoolStakedex, SplStakePoolStakedexWithWithdrawSol};
-use stakedex_unstake_it::{UnstakeItStakedex, UnstakeItStakedexPrefund};
+use stakedex_unstake_it::UnstakeItStakedexPrefund;
+
+mod builder;
+mod concurrent;
+mod depth;
+#[cfg(feature = "fetcher")]
+mod fetcher;
+mod incremental;
+mod on_demand;
+mod parallel;
+mod param_changes;
+mod route;
+mod snapshot;
+mod split;
+mod tx;
+mod update_report;
+
+use on_demand::{build_amms, AmmPools};
 
+pub use builder::*;
+pub use concurrent::*;
+pub use depth::*;
+#[cfg(feature = "fetcher")]
+pub use fetcher::*;
+pub use incremental::*;
+pub use on_demand::*;
+pub use param_changes::*;
+pub use route::*;
 pub use sanctum_lst_list::SanctumLstList;
+pub use snapshot::*;
+pub use split::*;
 pub use stakedex_interface::ID as stakedex_program_id;
+pub use stakedex_jup_interface::{AccountExistence, BridgeSeedAllocator, BridgeSeedSource};
+pub use stakedex_sdk_common::{
+    FeeRatio, PoolParam, PoolParamValue, PoolReadiness, StakedexSdkError, StaleQuoteErr,
+};
+pub use tx::*;
+pub use update_report::*;
 
 /// mainnet LUT that contains prefund accounts and other common accounts
 pub mod srlut {
@@ -59,13 +105,39 @@ macro_rules! match_same_stakedex {
     };
 }
 
-/// Collection of all supported stake pools
+/// Collection of all supported stake pools.
+///
+/// Each pool is behind an [`Arc`] and copied on write, so cloning is cheap and
+/// updating a clone only copies the pools that are updated, see [`ConcurrentStakedex`].
 #[derive(Clone, Default)]
 pub struct Stakedex {
-    pub spls: Vec<SplStakePoolStakedexWithWithdrawSol>,
-    pub unstakeit: UnstakeItStakedexPrefund,
-    pub marinade: MarinadeStakedex,
-    pub lido: LidoStakedex,
+    /// Private so that the indices into it stay valid, see [`Self::spls()`]
+    spls: Vec<Arc<SplStakePoolStakedexWithWithdrawSol>>,
+    pub unstakeit: Arc<UnstakeItStakedexPrefund>,
+    pub marinade: Arc<MarinadeStakedex>,
+    pub lido: Arc<LidoStakedex>,
+    /// { pool_mint: index into `spls` }
+    spl_mint_index: HashMap<Pubkey, usize>,
+    /// { stake_pool_addr: index into `spls` }
+    spl_main_state_index: HashMap<Pubkey, usize>,
+    /// Shared with the pools, from [`AmmContext::clock_ref`]
+    curr_epoch: Arc<AtomicU64>,
+    /// main_state_keys of pools excluded from quoting and [`Self::get_amms()`]
+    quarantined: HashSet<Pubkey>,
+    /// main_state_keys of the unstake.it, Marinade or Lido pools left out by [`StakedexBuilder`].
+    /// They remain in their default state and are skipped like quarantined pools, but are never updated.
+    excluded: HashSet<Pubkey>,
+    quarantine_failing_pools: bool,
+    /// { account: main_state_keys of the pools that read it on update }
+    account_index: HashMap<Pubkey, Vec<Pubkey>>,
+    /// Last seen data of the accounts in `account_index`, for [`Self::update_accounts()`]
+    account_cache: AccountMap,
+    /// See [`Self::set_param_change_listener()`]
+    param_change_listener: Option<PoolParamChangeListener>,
+    /// Whether epoch changes relink `self` and its pools to a new `curr_epoch`
+    /// instead of modifying the current one, which may be shared with snapshots.
+    /// See [`ConcurrentStakedex`]
+    relink_epoch_on_change: bool,
 }
 
 fn get_keyed_account(accounts: &AccountMap, key: &Pubkey) -> Result<KeyedAccount> {
@@ -88,10 +160,56 @@ fn init_from_keyed_account_no_params<P: InitFromKeyedAccount>(
     P::from_keyed_account(&keyed_acc, amm_context)
 }
 
+/// Falls back to the default pool if initialization fails
+fn init_from_keyed_account_or_default<P: InitFromKeyedAccount + Default>(
+    accounts: &AccountMap,
+    key: &Pubkey,
+    amm_context: &AmmContext,
+) -> (P, Option<anyhow::Error>) {
+    match init_from_keyed_account_no_params(accounts, key, amm_context) {
+        Ok(p) => (p, None),
+        Err(e) => (P::default(), Some(e)),
+    }
+}
+
+/// Copies `pool` if it is shared with a clone of its [`Stakedex`], unless it is excluded
+pub(crate) fn make_mut_unless_excluded<'a, P: BaseStakePoolAmm + Clone>(
+    pool: &'a mut Arc<P>,
+    excluded: &HashSet<Pubkey>,
+) -> Option<&'a mut P> {
+    if excluded.contains(&pool.main_state_key()) {
+        None
+    } else {
+        Some(Arc::make_mut(pool))
+    }
+}
+
+/// Leaves the pool in its default state without reading `accounts` if `main_state_key` is excluded
+fn init_unless_excluded<P: InitFromKeyedAccount + Default>(
+    excluded: &HashSet<Pubkey>,
+    main_state_key: &Pubkey,
+    accounts: &AccountMap,
+    key: &Pubkey,
+    amm_context: &AmmContext,
+) -> (P, Option<anyhow::Error>) {
+    if excluded.contains(main_state_key) {
+        return (P::default(), None);
+    }
+    init_from_keyed_account_or_default(accounts, key, amm_context)
+}
+
 impl Stakedex {
     /// Gets the list of accounts that must be fetched first to initialize
     /// Stakedex by passing the result into from_fetched_accounts()
     pub fn init_accounts<'a, I: Iterator<Item = &'a SanctumLst>>(sanctum_lsts: I) -> Vec<Pubkey> {
+        Self::init_accounts_excluding(sanctum_lsts, &HashSet::new())
+    }
+
+    /// [`Self::init_accounts()`] without the accounts of the `excluded` unstake.it, Marinade or Lido pools
+    pub(crate) fn init_accounts_excluding<'a>(
+        sanctum_lsts: impl Iterator<Item = &'a SanctumLst>,
+        excluded: &HashSet<Pubkey>,
+    ) -> Vec<Pubkey> {
         sanctum_lsts
             .filter_map(|lst| match lst.pool {
                 PoolInfo::SanctumSpl(accounts)
@@ -102,11 +220,16 @@ impl Stakedex {
                 | PoolInfo::ReservePool
                 | PoolInfo::SPool(..) => None,
             })
-            .chain([
-                unstake_it_program::SOL_RESERVES_ID,
-                marinade_state::ID,
-                lido_state::ID,
-            ])
+            .chain(
+                [
+                    (unstake_it_pool::ID, unstake_it_program::SOL_RESERVES_ID),
+                    (marinade_state::ID, marinade_state::ID),
+                    (lido_state::ID, lido_state::ID),
+                ]
+                .into_iter()
+                .filter(|(main_state_key, _)| !excluded.contains(main_state_key))
+                .map(|(_, init_account)| init_account),
+            )
             .collect()
     }
 
@@ -116,34 +239,55 @@ impl Stakedex {
         accounts: &AccountMap,
         amm_context: &AmmContext,
     ) -> (Self, Vec<anyhow::Error>) {
-        // So that stakedex is still useable even if some pools fail to load
-        let mut errs = Vec::new();
-
-        let unstakeit = UnstakeItStakedexPrefund(
-            init_from_keyed_account_no_params(
-                accounts,
-                &unstake_it_program::SOL_RESERVES_ID,
-                amm_context,
-            )
-            .unwrap_or_else(|e| {
-                errs.push(e);
-                UnstakeItStakedex::default()
-            }),
-        );
+        let (stakedex, report) =
+            Self::from_fetched_accounts_with_report(sanctum_lsts, accounts, amm_context);
+        (stakedex, report.into_errs())
+    }
 
-        let marinade =
-            init_from_keyed_account_no_params(accounts, &marinade_state::ID, amm_context)
-                .unwrap_or_else(|e| {
-                    errs.push(e);
-                    MarinadeStakedex::default()
-                });
+    /// Same as [`Self::from_fetched_accounts()`], but reports the outcome of each pool's initialization.
+    ///
+    /// Unstake.it, Marinade and Lido fall back to their default state if they fail to initialize,
+    /// while SPL stake pools that fail to initialize are not added.
+    pub fn from_fetched_accounts_with_report<'a>(
+        sanctum_lsts: impl Iterator<Item = &'a SanctumLst>,
+        accounts: &AccountMap,
+        amm_context: &AmmContext,
+    ) -> (Self, UpdateReport) {
+        Self::from_fetched_accounts_excluding(sanctum_lsts, HashSet::new(), accounts, amm_context)
+    }
 
-        let lido = init_from_keyed_account_no_params(accounts, &lido_state::ID, amm_context)
-            .unwrap_or_else(|e| {
-                errs.push(e);
-                LidoStakedex::default()
-            });
+    /// [`Self::from_fetched_accounts_with_report()`] leaving the `excluded` unstake.it, Marinade
+    /// or Lido pools in their default state, see [`StakedexBuilder`]
+    pub(crate) fn from_fetched_accounts_excluding<'a>(
+        sanctum_lsts: impl Iterator<Item = &'a SanctumLst>,
+        excluded: HashSet<Pubkey>,
+        accounts: &AccountMap,
+        amm_context: &AmmContext,
+    ) -> (Self, UpdateReport) {
+        // So that stakedex is still useable even if some pools fail to load
+        let (unstakeit, unstakeit_err) = init_unless_excluded(
+            &excluded,
+            &unstake_it_pool::ID,
+            accounts,
+            &unstake_it_program::SOL_RESERVES_ID,
+            amm_context,
+        );
+        let (marinade, marinade_err) = init_unless_excluded(
+            &excluded,
+            &marinade_state::ID,
+            accounts,
+            &marinade_state::ID,
+            amm_context,
+        );
+        let (lido, lido_err) = init_unless_excluded(
+            &excluded,
+            &lido_state::ID,
+            accounts,
+            &lido_state::ID,
+            amm_context,
+        );
 
+        let mut spl_init_failures = Vec::new();
         let spls = sanctum_lsts
             .filter_map(|lst| match lst.pool {
                 PoolInfo::SanctumSpl(spl_accs)
@@ -155,10 +299,14 @@ impl Stakedex {
                     get_keyed_account(accounts, &pool)
                         .map_or_else(Err, |mut ka| {
                             ka.params = Some(name.as_str().into());
-                            Ok(SplStakePoolStakedexWithWithdrawSol {
+                            Ok(Arc::new(SplStakePoolStakedexWithWithdrawSol {
                                 inner: SplStakePoolStakedex::from_keyed_account(&ka, amm_context)?,
                                 reserve_stake_lamports: None,
-                            })
+                            }))
+                        })
+                        .map_err(|e| {
+                            spl_init_failures
+                                .push(PoolUpdateReport::init_failed(pool, name, accounts, e))
                         })
                         .ok()
                 }
@@ -169,56 +317,317 @@ impl Stakedex {
             })
             .collect();
 
+        let mut stakedex = Self {
+            spls,
+            unstakeit: Arc::new(UnstakeItStakedexPrefund(unstakeit)),
+            marinade: Arc::new(marinade),
+            lido: Arc::new(lido),
+            curr_epoch: amm_context.clock_ref.epoch.clone(),
+            excluded,
+            ..Default::default()
+        };
+        stakedex.rebuild_spl_indices();
+
+        let mut pools: Vec<PoolUpdateReport> = stakedex
+            .spls
+            .iter()
+            .map(|spl| {
+                PoolUpdateReport::initialized(
+                    spl.as_ref(),
+                    &spl.inner.stake_pool_addr,
+                    accounts,
+                    None,
+                )
+            })
+            .collect();
+        let non_spl_pools = [
+            PoolUpdateReport::initialized(
+                stakedex.unstakeit.as_ref(),
+                &unstake_it_program::SOL_RESERVES_ID,
+                accounts,
+                unstakeit_err,
+            ),
+            PoolUpdateReport::initialized(
+                stakedex.marinade.as_ref(),
+                &marinade_state::ID,
+                accounts,
+                marinade_err,
+            ),
+            PoolUpdateReport::initialized(
+                stakedex.lido.as_ref(),
+                &lido_state::ID,
+                accounts,
+                lido_err,
+            ),
+        ];
+        pools.extend(
+            non_spl_pools
+                .into_iter()
+                .filter(|report| !stakedex.is_excluded(&report.main_state_key)),
+        );
+        pools.extend(spl_init_failures);
         (
-            Self {
-                spls,
-                unstakeit,
-                marinade,
-                lido,
+            stakedex,
+            UpdateReport {
+                pools,
+                errs: Vec::new(),
             },
-            errs,
         )
     }
 
+    /// Rebuilds the mint -> pool and main_state_key -> pool indices over `self.spls`,
+    /// and the account -> pools index used by [`Self::update_accounts()`].
+    ///
+    /// If multiple pools share the same mint, the first one in `self.spls` is used.
+    pub(crate) fn rebuild_spl_indices(&mut self) {
+        self.spl_mint_index.clear();
+        self.spl_main_state_index.clear();
+        for (i, spl) in self.spls.iter().enumerate() {
+            self.spl_mint_index
+                .entry(spl.inner.stake_pool.pool_mint)
+                .or_insert(i);
+            self.spl_main_state_index
+                .entry(spl.inner.stake_pool_addr)
+                .or_insert(i);
+        }
+        self.rebuild_account_index();
+    }
+
+    /// All SPL stake pools. Use [`Self::add_spl_pool()`], [`Self::replace_pool()`]
+    /// and [`Self::remove_pool()`] to modify them
+    pub fn spls(&self) -> &[Arc<SplStakePoolStakedexWithWithdrawSol>] {
+        &self.spls
+    }
+
+    pub fn get_spl_pool_by_mint(
+        &self,
+        mint: &Pubkey,
+    ) -> Option<&SplStakePoolStakedexWithWithdrawSol> {
+        self.spl_mint_index
+            .get(mint)
+            .and_then(|i| self.spls.get(*i))
+            .map(Arc::as_ref)
+    }
+
+    pub fn get_spl_pool_by_main_state_key(
+        &self,
+        main_state_key: &Pubkey,
+    ) -> Option<&SplStakePoolStakedexWithWithdrawSol> {
+        self.spl_main_state_index
+            .get(main_state_key)
+            .and_then(|i| self.spls.get(*i))
+            .map(Arc::as_ref)
+    }
+
+    pub fn curr_epoch(&self) -> u64 {
+        self.curr_epoch.load(Ordering::Relaxed)
+    }
+
+    /// Sets the current epoch of `self` and all its pools
+    pub fn update_clock(&mut self, clock: &Clock) {
+        if self.relink_epoch_on_change && clock.epoch != self.curr_epoch() {
+            self.relink_curr_epoch(clock.epoch);
+        } else {
+            self.curr_epoch.store(clock.epoch, Ordering::Relaxed);
+        }
+    }
+
+    /// Links `self` and all its pools to a new `curr_epoch` set to `epoch`,
+    /// copying the pools shared with other [`Stakedex`]es
+    pub(crate) fn relink_curr_epoch(&mut self, epoch: u64) {
+        let curr_epoch = Arc::new(AtomicU64::new(epoch));
+        for spl in self.spls.iter_mut() {
+            Arc::make_mut(spl).inner.curr_epoch = curr_epoch.clone();
+        }
+        Arc::make_mut(&mut self.lido).set_curr_epoch(curr_epoch.clone());
+        self.curr_epoch = curr_epoch;
+    }
+
+    /// The [`PoolReadiness`] of every pool, to tell why routes involving a pool are unavailable,
+    /// e.g. right after an epoch rollover
+    pub fn pool_readiness(&self) -> impl Iterator<Item = (&dyn BaseStakePoolAmm, PoolReadiness)> {
+        self.all_pools().map(|p| (p, p.readiness()))
+    }
+
+    /// The [`PoolReadiness`] of the pool of `mint`, `None` if there is no such pool
+    pub fn pool_readiness_of_mint(&self, mint: &Pubkey) -> Option<PoolReadiness> {
+        self.all_pools()
+            .find(|p| p.staked_sol_mint() == *mint)
+            .map(|p| p.readiness())
+    }
+
+    /// Adds a new SPL stake pool from its fetched stake pool account.
+    ///
+    /// `label` is the token name used for the stake pool label, e.g. "jitoSOL".
+    ///
+    /// The pool is not usable until [`Self::update()`] has been called with its
+    /// [`BaseStakePoolAmm::get_accounts_to_update()`] fetched.
+    ///
+    /// Errs if a pool with the same stake pool address or mint already exists,
+    /// use [`Self::replace_pool()`] to replace it instead.
+    pub fn add_spl_pool(
+        &mut self,
+        keyed_account: &KeyedAccount,
+        label: &str,
+    ) -> Result<(), StakedexSdkError> {
+        let pool = self.init_spl_pool(keyed_account, label)?;
+        if self
+            .get_spl_pool_by_main_state_key(&pool.inner.stake_pool_addr)
+            .is_some()
+        {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "stake pool {} already exists",
+                pool.inner.stake_pool_addr
+            )));
+        }
+        if self
+            .get_spl_pool_by_mint(&pool.inner.stake_pool.pool_mint)
+            .is_some()
+        {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "stake pool for mint {} already exists",
+                pool.inner.stake_pool.pool_mint
+            )));
+        }
+        self.spls.push(Arc::new(pool));
+        self.rebuild_spl_indices();
+        Ok(())
+    }
+
+    /// Removes the SPL stake pool of `mint`, returning it.
+    ///
+    /// Only SPL stake pools can be removed, returns `None` for other mints.
+    pub fn remove_pool(&mut self, mint: &Pubkey) -> Option<SplStakePoolStakedexWithWithdrawSol> {
+        let i = *self.spl_mint_index.get(mint)?;
+        let removed = self.spls.remove(i);
+        self.quarantined.remove(&removed.inner.stake_pool_addr);
+        self.rebuild_spl_indices();
+        Some(Arc::unwrap_or_clone(removed))
+    }
+
+    /// Replaces the SPL stake pool with the same stake pool address as `keyed_account`,
+    /// or adds it if it doesn't exist yet. Returns the replaced pool, if any.
+    ///
+    /// Like [`Self::add_spl_pool()`], the new pool must be updated before it is usable.
+    pub fn replace_pool(
+        &mut self,
+        keyed_account: &KeyedAccount,
+        label: &str,
+    ) -> Result<Option<SplStakePoolStakedexWithWithdrawSol>, StakedexSdkError> {
+        let pool = self.init_spl_pool(keyed_account, label)?;
+        let mint_owner = self
+            .spl_mint_index
+            .get(&pool.inner.stake_pool.pool_mint)
+            .map(|i| self.spls[*i].inner.stake_pool_addr);
+        if mint_owner.is_some_and(|addr| addr != pool.inner.stake_pool_addr) {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "stake pool for mint {} already exists",
+                pool.inner.stake_pool.pool_mint
+            )));
+        }
+        let replaced = match self.spl_main_state_index.get(&pool.inner.stake_pool_addr) {
+            Some(i) => Some(Arc::unwrap_or_clone(std::mem::replace(
+                &mut self.spls[*i],
+                Arc::new(pool),
+            ))),
+            None => {
+                self.spls.push(Arc::new(pool));
+                None
+            }
+        };
+        self.rebuild_spl_indices();
+        Ok(replaced)
+    }
+
+    fn init_spl_pool(
+        &self,
+        keyed_account: &KeyedAccount,
+        label: &str,
+    ) -> Result<SplStakePoolStakedexWithWithdrawSol, StakedexSdkError> {
+        let mut ka = keyed_account.clone();
+        ka.params = Some(label.into());
+        let amm_context = AmmContext {
+            clock_ref: ClockRef {
+                epoch: self.curr_epoch.clone(),
+                ..Default::default()
+            },
+        };
+        Ok(SplStakePoolStakedexWithWithdrawSol {
+            inner: SplStakePoolStakedex::from_keyed_account(&ka, &amm_context)?,
+            reserve_stake_lamports: None,
+        })
+    }
+
+    /// Whether the pool of `main_state_key` was left out by [`StakedexBuilder`]
+    pub fn is_excluded(&self, main_state_key: &Pubkey) -> bool {
+        self.excluded.contains(main_state_key)
+    }
+
+    /// Excludes the pools left out by [`StakedexBuilder`]
     pub fn all_pools(&self) -> impl Iterator<Item = &dyn BaseStakePoolAmm> {
         self.spls
             .iter()
-            .map(|spl| spl as &dyn BaseStakePoolAmm)
-            .chain([
-                &self.unstakeit as &dyn BaseStakePoolAmm,
-                &self.marinade as &dyn BaseStakePoolAmm,
-                &self.lido as &dyn BaseStakePoolAmm,
-            ])
+            .map(|spl| spl.as_ref() as &dyn BaseStakePoolAmm)
+            .chain(
+                [
+                    self.unstakeit.as_ref() as &dyn BaseStakePoolAmm,
+                    self.marinade.as_ref() as &dyn BaseStakePoolAmm,
+                    self.lido.as_ref() as &dyn BaseStakePoolAmm,
+                ]
+                .into_iter()
+                .filter(move |p| !self.is_excluded(&p.main_state_key())),
+            )
     }
 
+    /// Copies each pool that is shared with a clone of `self` only once it is iterated over
+    ///
+    /// SPL stake pools whose mint changes are only reindexed on the next [`Self::update()`]
     pub fn all_pools_mut(&mut self) -> impl Iterator<Item = &mut dyn BaseStakePoolAmm> {
-        self.spls
-            .iter_mut()
-            .map(|spl| spl as &mut dyn BaseStakePoolAmm)
-            .chain([
-                &mut self.unstakeit as &mut dyn BaseStakePoolAmm,
-                &mut self.marinade as &mut dyn BaseStakePoolAmm,
-                &mut self.lido as &mut dyn BaseStakePoolAmm,
-            ])
+        let Self {
+            spls,
+            unstakeit,
+            marinade,
+            lido,
+            excluded,
+            ..
+        } = self;
+        let excluded = &*excluded;
+        spls.iter_mut()
+            .map(|spl| Arc::make_mut(spl) as &mut dyn BaseStakePoolAmm)
+            .chain(
+                iter::once_with(move || {
+                    make_mut_unless_excluded(unstakeit, excluded)
+                        .map(|p| p as &mut dyn BaseStakePoolAmm)
+                })
+                .chain(iter::once_with(move || {
+                    make_mut_unless_excluded(marinade, excluded)
+                        .map(|p| p as &mut dyn BaseStakePoolAmm)
+                }))
+                .chain(iter::once_with(move || {
+                    make_mut_unless_excluded(lido, excluded).map(|p| p as &mut dyn BaseStakePoolAmm)
+                }))
+                .flatten(),
+            )
     }
 
+    /// Includes the clock sysvar so that [`Self::update()`] can track epoch rollovers
     pub fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        self.all_pools().fold(Vec::new(), |mut vec, p| {
-            vec.append(&mut p.get_accounts_to_update());
-            vec
-        })
+        self.all_pools()
+            .fold(vec![sysvar::clock::ID], |mut vec, p| {
+                vec.append(&mut p.get_accounts_to_update());
+                vec
+            })
     }
 
+    /// Updates the current epoch from the clock sysvar if it's in `account_map`,
+    /// then updates all pools, in parallel if the `rayon` feature is enabled
+    ///
+    /// See [`Self::update_with_report()`] for the outcome of each pool's update
     pub fn update(&mut self, account_map: &AccountMap) -> Vec<anyhow::Error> {
-        // accumulate errs in a vec so that other pools are still updated even if some pools fail to update
-        self.all_pools_mut().fold(Vec::new(), |mut err_vec, p| {
-            if let Err(e) = p.update(account_map) {
-                err_vec.push(e);
-            }
-            err_vec
-        })
+        self.update_with_report(account_map).into_errs()
     }
 
+    /// Copies unstake.it's state without heap-allocating
     pub fn prefund_repay_params(&self) -> PrefundRepayParams {
         PrefundRepayParams {
             fee: self.unstakeit.0.fee.fee.clone(),
@@ -228,75 +637,83 @@ impl Stakedex {
         }
     }
 
-    // TODO: maybe build an index of { mint: SplStakePoolStakedex } for faster lookups
-    // when there be many SplStakePoolStakedexes in the future
+    /// Like [`Self::get_spl_pool_by_mint()`], but `None` if the pool is quarantined.
+    /// The get_*_pool() fns below similarly exclude quarantined pools from quoting.
+    fn get_unquarantined_spl_pool_by_mint(
+        &self,
+        mint: &Pubkey,
+    ) -> Option<&SplStakePoolStakedexWithWithdrawSol> {
+        self.unless_quarantined(self.get_spl_pool_by_mint(mint)?)
+    }
+
     pub fn get_deposit_sol_pool(&self, mint: &Pubkey) -> Option<&dyn DepositSol> {
         Some(match *mint {
-            msol::ID => &self.marinade,
-            mint => self.spls.iter().find(
-                |SplStakePoolStakedexWithWithdrawSol {
-                     inner: SplStakePoolStakedex { stake_pool, .. },
-                     ..
-                 }| stake_pool.pool_mint == mint,
-            )?,
+            msol::ID => self.unless_quarantined(self.marinade.as_ref())?,
+            mint => self.get_unquarantined_spl_pool_by_mint(&mint)?,
         })
     }
 
     pub fn get_withdraw_sol_pool(&self, mint: &Pubkey) -> Option<&dyn WithdrawSol> {
         // right now only spls can WithdrawSol
-        self.spls
-            .iter()
-            .find(
-                |SplStakePoolStakedexWithWithdrawSol {
-                     inner: SplStakePoolStakedex { stake_pool, .. },
-                     ..
-                 }| stake_pool.pool_mint == *mint,
-            )
+        self.get_unquarantined_spl_pool_by_mint(mint)
             .map(|sp| sp as &dyn WithdrawSol)
     }
 
     pub fn get_deposit_stake_pool(&self, mint: &Pubkey) -> Option<&dyn DepositStake> {
         Some(match *mint {
-            msol::ID => &self.marinade,
-            native_mint::ID => &self.unstakeit,
-            mint => self.spls.iter().find(
-                |SplStakePoolStakedexWithWithdrawSol {
-                     inner: SplStakePoolStakedex { stake_pool, .. },
-                     ..
-                 }| stake_pool.pool_mint == mint,
-            )?,
+            msol::ID => self.unless_quarantined(self.marinade.as_ref())?,
+            native_mint::ID => self.unless_quarantined(self.unstakeit.as_ref())?,
+            mint => self.get_unquarantined_spl_pool_by_mint(&mint)?,
         })
     }
 
     pub fn get_withdraw_stake_pool(&self, mint: &Pubkey) -> Option<&dyn WithdrawStake> {
         Some(match *mint {
-            stsol::ID => &self.lido,
-            mint => self.spls.iter().find(
-                |SplStakePoolStakedexWithWithdrawSol {
-                     inner: SplStakePoolStakedex { stake_pool, .. },
-                     ..
-                 }| stake_pool.pool_mint == mint,
-            )?,
+            stsol::ID => self.unless_quarantined(self.lido.as_ref())?,
+            mint => self.get_unquarantined_spl_pool_by_mint(&mint)?,
         })
     }
 
-    pub fn quote_swap_via_stake(&self, quote_params: &QuoteParams) -> Result<Quote> {
+    /// PrefundSwapViaStake is prefunded by unstake.it so it is unavailable
+    /// while unstake.it is quarantined or excluded
+    fn check_prefund_available(&self) -> Result<(), StakedexSdkError> {
+        let unstakeit = self.unstakeit.main_state_key();
+        if self.is_excluded(&unstakeit) {
+            return Err(StakedexSdkError::NoRouteFound);
+        }
+        if self.is_quarantined(&unstakeit) {
+            return Err(StakedexSdkError::PoolQuarantined(unstakeit));
+        }
+        Ok(())
+    }
+
+    pub fn quote_swap_via_stake(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote, StakedexSdkError> {
+        self.quote_swap_via_stake_detailed(quote_params, &|_| true)
+            .map(|q| q.quote)
+    }
+
+    /// Only withdraws stake delegated to vote accounts that `voter_filter` returns true for
+    fn quote_swap_via_stake_detailed(
+        &self,
+        quote_params: &QuoteParams,
+        voter_filter: &dyn Fn(&Pubkey) -> bool,
+    ) -> Result<PoolPairQuote, StakedexSdkError> {
+        self.check_prefund_available()?;
         let withdraw_from = self
             .get_withdraw_stake_pool(&quote_params.input_mint)
-            .ok_or_else(|| anyhow!("pool not found for input mint {}", quote_params.input_mint))?;
+            .ok_or(StakedexSdkError::UnknownMint(quote_params.input_mint))?;
         let deposit_to = self
             .get_deposit_stake_pool(&quote_params.output_mint)
-            .ok_or_else(|| {
-                anyhow!(
-                    "pool not found for output mint {}",
-                    quote_params.output_mint
-                )
-            })?;
-        quote_pool_pair(
+            .ok_or(StakedexSdkError::UnknownMint(quote_params.output_mint))?;
+        quote_pool_pair_detailed_for_voters(
             quote_params,
             &self.prefund_repay_params(),
             withdraw_from,
             deposit_to,
+            voter_filter,
         )
     }
 
@@ -304,18 +721,34 @@ impl Stakedex {
         &self,
         swap_params: &SwapParams,
         bridge_stake_seed: u32,
-    ) -> Result<[Instruction; 2]> {
+    ) -> Result<[Instruction; 2], StakedexSdkError> {
+        self.check_prefund_available()?;
         let withdraw_from = self
             .get_withdraw_stake_pool(&swap_params.source_mint)
-            .ok_or_else(|| anyhow!("pool not found for src mint {}", swap_params.source_mint))?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.source_mint))?;
         let deposit_to = self
             .get_deposit_stake_pool(&swap_params.destination_mint)
-            .ok_or_else(|| {
-                anyhow!(
-                    "pool not found for dst mint {}",
-                    swap_params.destination_mint
-                )
-            })?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.destination_mint))?;
+        let metas = manual_concat_get_account_metas(
+            swap_params,
+            &self.prefund_repay_params(),
+            withdraw_from,
+            deposit_to,
+            bridge_stake_seed,
+        )?;
+        Self::manual_concat_prefund_swap_via_stake_ixs_from_metas(
+            swap_params,
+            bridge_stake_seed,
+            metas,
+        )
+    }
+
+    /// `metas` must be the output of [`manual_concat_get_account_metas()`]
+    fn manual_concat_prefund_swap_via_stake_ixs_from_metas(
+        swap_params: &SwapParams,
+        bridge_stake_seed: u32,
+        metas: Vec<AccountMeta>,
+    ) -> Result<[Instruction; 2], StakedexSdkError> {
         let mut prefund_withdraw_stake_ix = stakedex_interface::prefund_withdraw_stake_ix(
             // dont cares for keys, since we replace them with
             // get_account_metas()
@@ -355,13 +788,6 @@ impl Stakedex {
                 dest_token_mint: Pubkey::default(),
             },
         )?;
-        let metas = manual_concat_get_account_metas(
-            swap_params,
-            &self.prefund_repay_params(),
-            withdraw_from,
-            deposit_to,
-            bridge_stake_seed,
-        )?;
         let split_at = metas
             .iter()
             .position(|meta| *meta == swap_params.placeholder_account_meta())
@@ -375,18 +801,30 @@ impl Stakedex {
         &self,
         swap_params: &SwapParams,
         bridge_stake_seed: u32,
-    ) -> Result<Instruction> {
+    ) -> Result<Instruction, StakedexSdkError> {
+        self.check_prefund_available()?;
         let withdraw_from = self
             .get_withdraw_stake_pool(&swap_params.source_mint)
-            .ok_or_else(|| anyhow!("pool not found for src mint {}", swap_params.source_mint))?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.source_mint))?;
         let deposit_to = self
             .get_deposit_stake_pool(&swap_params.destination_mint)
-            .ok_or_else(|| {
-                anyhow!(
-                    "pool not found for dst mint {}",
-                    swap_params.destination_mint
-                )
-            })?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.destination_mint))?;
+        let metas = prefund_get_account_metas(
+            swap_params,
+            &self.prefund_repay_params(),
+            withdraw_from,
+            deposit_to,
+            bridge_stake_seed,
+        )?;
+        Self::prefund_swap_via_stake_ix_from_metas(swap_params, bridge_stake_seed, metas)
+    }
+
+    /// `metas` must be the output of [`prefund_get_account_metas()`]
+    fn prefund_swap_via_stake_ix_from_metas(
+        swap_params: &SwapParams,
+        bridge_stake_seed: u32,
+        metas: Vec<AccountMeta>,
+    ) -> Result<Instruction, StakedexSdkError> {
         let mut ix = stakedex_interface::prefund_swap_via_stake_ix(
             // dont cares for keys, since we replace them with
             // get_account_metas()
@@ -418,40 +856,34 @@ impl Stakedex {
                 },
             },
         )?;
-        // TODO: this is doing the same computation as it did in quote, should we cache this somehow?
-        ix.accounts = prefund_get_account_metas(
-            swap_params,
-            &self.prefund_repay_params(),
-            withdraw_from,
-            deposit_to,
-            bridge_stake_seed,
-        )?;
+        ix.accounts = metas;
         Ok(ix)
     }
 
-    pub fn quote_stake_wrapped_sol(&self, quote_params: &QuoteParams) -> Result<Quote> {
+    pub fn quote_stake_wrapped_sol(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote, StakedexSdkError> {
         let deposit_to = self
             .get_deposit_sol_pool(&quote_params.output_mint)
-            .ok_or_else(|| {
-                anyhow!(
-                    "pool not found for output mint {}",
-                    quote_params.output_mint
-                )
-            })?;
-        let deposit_sol_quote = deposit_to.get_deposit_sol_quote(quote_params.amount)?;
+            .ok_or(StakedexSdkError::UnknownMint(quote_params.output_mint))?;
+        let deposit_sol_quote = match quote_params.swap_mode {
+            SwapMode::ExactIn => deposit_to.get_deposit_sol_quote(quote_params.amount)?,
+            SwapMode::ExactOut => {
+                deposit_to.get_deposit_sol_quote_exact_out(quote_params.amount)?
+            }
+        };
         let quote = deposit_to.convert_quote(deposit_sol_quote);
         Ok(quote)
     }
 
-    pub fn stake_wrapped_sol_ix(&self, swap_params: &SwapParams) -> Result<Instruction> {
+    pub fn stake_wrapped_sol_ix(
+        &self,
+        swap_params: &SwapParams,
+    ) -> Result<Instruction, StakedexSdkError> {
         let deposit_to = self
             .get_deposit_sol_pool(&swap_params.destination_mint)
-            .ok_or_else(|| {
-                anyhow!(
-                    "pool not found for dst mint {}",
-                    swap_params.destination_mint
-                )
-            })?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.destination_mint))?;
         let mut ix = stakedex_interface::stake_wrapped_sol_ix(
             StakeWrappedSolKeys {
                 user: swap_params.token_transfer_authority,
@@ -463,7 +895,7 @@ impl Stakedex {
                 system_program: system_program::ID,
                 wsol_bridge_in: wsol_bridge_in::ID,
                 sol_bridge_out: stakedex_program::SOL_BRIDGE_OUT_ID,
-                dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+                dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
             },
             StakeWrappedSolIxArgs {
                 amount: swap_params.in_amount,
@@ -474,19 +906,30 @@ impl Stakedex {
         Ok(ix)
     }
 
-    pub fn quote_withdraw_wrapped_sol(&self, quote_params: &QuoteParams) -> Result<Quote> {
+    pub fn quote_withdraw_wrapped_sol(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote, StakedexSdkError> {
         let withdraw_from = self
             .get_withdraw_sol_pool(&quote_params.input_mint)
-            .ok_or_else(|| anyhow!("pool not found for input mint {}", quote_params.input_mint))?;
-        let withdraw_sol_quote = withdraw_from.get_withdraw_sol_quote(quote_params.amount)?;
+            .ok_or(StakedexSdkError::UnknownMint(quote_params.input_mint))?;
+        let withdraw_sol_quote = match quote_params.swap_mode {
+            SwapMode::ExactIn => withdraw_from.get_withdraw_sol_quote(quote_params.amount)?,
+            SwapMode::ExactOut => {
+                withdraw_from.get_withdraw_sol_quote_exact_out(quote_params.amount)?
+            }
+        };
         let quote = withdraw_from.convert_quote(withdraw_sol_quote);
         Ok(quote)
     }
 
-    pub fn withdraw_wrapped_sol_ix(&self, swap_params: &SwapParams) -> Result<Instruction> {
+    pub fn withdraw_wrapped_sol_ix(
+        &self,
+        swap_params: &SwapParams,
+    ) -> Result<Instruction, StakedexSdkError> {
         let withdraw_from = self
             .get_withdraw_sol_pool(&swap_params.source_mint)
-            .ok_or_else(|| anyhow!("pool not found for src mint {}", swap_params.source_mint))?;
+            .ok_or(StakedexSdkError::UnknownMint(swap_params.source_mint))?;
         let mut ix = stakedex_interface::withdraw_wrapped_sol_ix(
             WithdrawWrappedSolKeys {
                 user: swap_params.token_transfer_authority,
@@ -507,13 +950,47 @@ impl Stakedex {
     }
 
     /// input_mint = voter pubkey for deposit stake
-    pub fn quote_deposit_stake(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        let (deposit_to, dsq) = self.quote_deposit_stake_dsq(
-            &quote_params.output_mint,
-            &quote_params.input_mint,
-            quote_params.amount,
-        )?;
-        Ok(deposit_to.convert_deposit_stake_quote(quote_params.amount, dsq))
+    ///
+    /// For ExactOut, `in_amount` of the returned quote is the
+    /// minimum lamports the stake account to be deposited must have
+    pub fn quote_deposit_stake(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<Quote, StakedexSdkError> {
+        self.quote_deposit_stake_detailed(quote_params)
+            .map(|(quote, _dsq)| quote)
+    }
+
+    fn quote_deposit_stake_detailed(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<(Quote, DepositStakeQuote), StakedexSdkError> {
+        match quote_params.swap_mode {
+            SwapMode::ExactIn => {
+                let (deposit_to, dsq) = self.quote_deposit_stake_dsq(
+                    &quote_params.output_mint,
+                    &quote_params.input_mint,
+                    quote_params.amount,
+                )?;
+                Ok((
+                    deposit_to.convert_deposit_stake_quote(quote_params.amount, dsq),
+                    dsq,
+                ))
+            }
+            SwapMode::ExactOut => {
+                let deposit_to = self
+                    .get_deposit_stake_pool(&quote_params.output_mint)
+                    .ok_or(StakedexSdkError::UnknownMint(quote_params.output_mint))?;
+                let (wsq, dsq) = deposit_to.get_deposit_stake_quote_exact_out(
+                    quote_params.amount,
+                    quote_params.input_mint,
+                )?;
+                Ok((
+                    deposit_to.convert_deposit_stake_quote(wsq.lamports_out, dsq),
+                    dsq,
+                ))
+            }
+        }
     }
 
     /// Inner fn for [`Self::quote_deposit_stake()`].
@@ -523,36 +1000,47 @@ impl Stakedex {
         output_mint: &Pubkey,
         voter: &Pubkey,
         in_amount: u64,
-    ) -> Result<(&dyn DepositStake, DepositStakeQuote)> {
+    ) -> Result<(&dyn DepositStake, DepositStakeQuote), StakedexSdkError> {
         let deposit_to = self
             .get_deposit_stake_pool(output_mint)
-            .ok_or_else(|| anyhow!("pool not found for output mint {}", output_mint))?;
+            .ok_or(StakedexSdkError::UnknownMint(*output_mint))?;
         let wsq = WithdrawStakeQuote::from_lamports_and_voter(in_amount, *voter);
         let dsq = deposit_to.get_deposit_stake_quote(wsq)?;
         if dsq.is_zero_out() {
-            return Err(anyhow!("pool cannot accept stake account"));
+            return Err(StakedexSdkError::ValidatorNotAccepted);
         }
         Ok((deposit_to, dsq))
     }
 
     /// source_mint = voter pubkey for stake acc to be deposited
     /// source_token_account = stake acc to be deposited
-    pub fn deposit_stake_ix(&self, swap_params: &SwapParams) -> Result<Instruction> {
+    pub fn deposit_stake_ix(
+        &self,
+        swap_params: &SwapParams,
+    ) -> Result<Instruction, StakedexSdkError> {
         let (deposit_to, dsq) = self.quote_deposit_stake_dsq(
             &swap_params.destination_mint,
             &swap_params.source_mint,
             swap_params.in_amount,
         )?;
+        Self::deposit_stake_ix_from_dsq(swap_params, deposit_to, &dsq)
+    }
+
+    fn deposit_stake_ix_from_dsq(
+        swap_params: &SwapParams,
+        deposit_to: &dyn DepositStake,
+        dsq: &DepositStakeQuote,
+    ) -> Result<Instruction, StakedexSdkError> {
         let stake_account = swap_params.source_token_account;
         let mut ix = stakedex_interface::deposit_stake_ix(DepositStakeKeys {
             user: swap_params.token_transfer_authority,
             stake_account,
             dest_token_to: swap_params.destination_token_account,
-            dest_token_fee_token_account: find_fee_token_acc(&swap_params.destination_mint).0,
+            dest_token_fee_token_account: fee_token_acc(&swap_params.destination_mint),
             dest_token_mint: swap_params.destination_mint,
         })?;
         let deposit_to_virtual_ix = deposit_to.virtual_ix(
-            &dsq,
+            dsq,
             &DepositStakeInfo {
                 addr: stake_account,
             },
@@ -564,89 +1052,56 @@ impl Stakedex {
         Ok(ix)
     }
 
-    /// Creates all possible Amms from the underlying available Stakedexes
+    /// Creates all possible Amms from the underlying available Stakedexes,
+    /// excluding quarantined pools and those left out by [`StakedexBuilder`].
+    ///
+    /// Amms containing the same pool share its state via [`stakedex_jup_interface::SharedPool`],
+    /// which is updated by whichever of them is updated with new account data first.
+    ///
+    /// See [`Self::amms_for_mints()`] to only create the Amms of some pools.
     pub fn get_amms(self) -> Vec<Box<dyn Amm + Send + Sync>> {
-        #[derive(Clone)]
-        enum Stakedex {
-            SplStakePool(SplStakePoolStakedexWithWithdrawSol),
-            UnstakeIt(UnstakeItStakedexPrefund),
-            Marinade(MarinadeStakedex),
-            Lido(LidoStakedex),
-        }
+        self.into_amms(None)
+    }
 
+    /// Same as [`Self::get_amms()`], but the pair Amms pick their swaps' `bridge_stake_seed`s
+    /// with `bridge_seed_source` instead of at random, see [`BridgeSeedAllocator::into_source()`]
+    pub fn get_amms_with_bridge_seed_source(
+        self,
+        bridge_seed_source: BridgeSeedSource,
+    ) -> Vec<Box<dyn Amm + Send + Sync>> {
+        self.into_amms(Some(bridge_seed_source))
+    }
+
+    fn into_amms(
+        self,
+        bridge_seed_source: Option<BridgeSeedSource>,
+    ) -> Vec<Box<dyn Amm + Send + Sync>> {
         let Self {
             spls,
             unstakeit,
             marinade,
             lido,
+            mut quarantined,
+            excluded,
+            ..
         } = self;
-
-        let stakedexes: Vec<Stakedex> = spls
-            .into_iter()
-            .map(Stakedex::SplStakePool)
-            .chain([
-                Stakedex::UnstakeIt(unstakeit),
-                Stakedex::Marinade(marinade),
-                Stakedex::Lido(lido),
-            ])
-            .collect();
-
-        let mut amm_keys = HashSet::new();
-        let mut amms: Vec<Box<dyn Amm + Send + Sync>> = Vec::new();
-        let mut add_amm_if_new_key = |amm: Box<dyn Amm + Send + Sync>| {
-            let amm_key = amm.key();
-            if !amm_keys.contains(&amm_key) {
-                amm_keys.insert(amm_key);
-                amms.push(amm);
-            }
-        };
-
-        for stakedex in stakedexes.iter() {
-            let amm: Box<dyn Amm + Send + Sync> = match stakedex {
-                Stakedex::SplStakePool(spl_stake_pool) => {
-                    Box::new(DepositWithdrawSolWrapper(spl_stake_pool.clone()))
-                }
-                Stakedex::Marinade(marinade) => Box::new(DepositSolWrapper(marinade.clone())),
-                // non-DepositSol
-                Stakedex::UnstakeIt(_) => continue,
-                Stakedex::Lido(_) => continue,
-            };
-            add_amm_if_new_key(amm);
-        }
-
-        // SplStakePool WithdrawStake + DepositStake
-        // UnstakeIt DepositStake
-        // Marinade DepositStake
-        // Lido WithdrawStake
-        for (first_stakedex, second_stakedex) in stakedexes.into_iter().tuple_combinations() {
-            let amm: Box<dyn Amm + Send + Sync> = match (first_stakedex, second_stakedex) {
-                (Stakedex::SplStakePool(p1), Stakedex::SplStakePool(p2)) => {
-                    Box::new(TwoWayPoolPair::new(p1, p2))
-                }
-                match_stakedexes!(SplStakePool, Marinade, withdraw, deposit) => {
-                    Box::new(OneWayPoolPair::new(withdraw, deposit))
-                }
-                match_stakedexes!(SplStakePool, UnstakeIt, withdraw, deposit) => {
-                    Box::new(OneWayPoolPair::new(withdraw, deposit))
-                }
-                match_stakedexes!(Lido, SplStakePool, withdraw, deposit) => {
-                    Box::new(OneWayPoolPair::new(withdraw, deposit))
-                }
-                match_stakedexes!(Lido, UnstakeIt, withdraw, deposit) => {
-                    Box::new(OneWayPoolPair::new(withdraw, deposit))
-                }
-                match_stakedexes!(Lido, Marinade, withdraw, deposit) => {
-                    Box::new(OneWayPoolPair::new(withdraw, deposit))
-                }
-                match_stakedexes!(Marinade, UnstakeIt, _, _) => continue, // Cannot do anything with those two
-                match_same_stakedex!(UnstakeIt)
-                | match_same_stakedex!(Marinade)
-                | match_same_stakedex!(Lido) => continue, // Invalid if found
-            };
-            add_amm_if_new_key(amm);
-        }
-
-        amms
+        // excluded pools are skipped the same way
+        quarantined.extend(excluded);
+        build_amms(AmmPools {
+            spls: spls
+                .into_iter()
+                .filter(|spl| !quarantined.contains(&spl.inner.stake_pool_addr))
+                .map(Arc::unwrap_or_clone)
+                .collect(),
+            unstakeit: (!quarantined.contains(&unstakeit.main_state_key()))
+                .then(|| Arc::unwrap_or_clone(unstakeit)),
+            marinade: (!quarantined.contains(&marinade.main_state_key()))
+                .then(|| Arc::unwrap_or_clone(marinade)),
+            lido: (!quarantined.contains(&lido.main_state_key()))
+                .then(|| Arc::unwrap_or_clone(lido)),
+            with_deposit_sol: true,
+            bridge_seed_source,
+        })
     }
 }
 
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use jupiter_amm_interface::Amm;
+use solana_sdk::{pubkey::Pubkey, sysvar};
+use spl_token::native_mint;
+use stakedex_jup_interface::{
+    BridgeSeedSource, DepositSolWrapper, DepositWithdrawSolWrapper, OneWayPoolPair, SharedPool,
+    TwoWayPoolPair,
+};
+use stakedex_lido::LidoStakedex;
+use stakedex_marinade::MarinadeStakedex;
+use stakedex_sdk_common::{BaseStakePoolAmm, StakedexSdkError};
+use stakedex_spl_stake_pool::SplStakePoolStakedexWithWithdrawSol;
+use stakedex_unstake_it::UnstakeItStakedexPrefund;
+
+use crate::{match_same_stakedex, match_stakedexes, Stakedex};
+
+/// Pools to create Amms from, see [`build_amms()`]
+#[derive(Default)]
+pub(crate) struct AmmPools {
+    pub spls: Vec<SplStakePoolStakedexWithWithdrawSol>,
+    pub unstakeit: Option<UnstakeItStakedexPrefund>,
+    pub marinade: Option<MarinadeStakedex>,
+    pub lido: Option<LidoStakedex>,
+
+    /// Whether to create the SOL <-> LST Amms of `spls` and `marinade`
+    pub with_deposit_sol: bool,
+
+    /// Given to every pair Amm, see [`TwoWayPoolPair::with_bridge_seed_source()`]
+    pub bridge_seed_source: Option<BridgeSeedSource>,
+}
+
+impl Stakedex {
+    /// Creates only the Amm that swaps `input_mint` for `output_mint`,
+    /// without consuming `self` or creating any of the other Amms.
+    ///
+    /// The Amm owns a copy of its pools, so it must be updated with
+    /// [`accounts_to_update_for_amms()`] independently of `self`.
+    /// The current epoch remains shared with `self`, see [`Self::update_clock()`].
+    ///
+    /// For SOL <-> LST, the pool's DepositSol/WithdrawSol Amm is preferred over
+    /// going through unstake.it's DepositStake.
+    pub fn amm_for_pair(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+    ) -> Result<Box<dyn Amm + Send + Sync>, StakedexSdkError> {
+        for mint in [input_mint, output_mint] {
+            if !self.has_unquarantined_pool_of_mint(mint) {
+                return Err(StakedexSdkError::UnknownMint(*mint));
+            }
+        }
+        self.amms_for_mints(&[*input_mint, *output_mint])
+            .into_iter()
+            .find(|amm| {
+                let reserve_mints = amm.get_reserve_mints();
+                if amm.unidirectional() {
+                    reserve_mints == [*input_mint, *output_mint]
+                } else {
+                    reserve_mints.contains(input_mint) && reserve_mints.contains(output_mint)
+                }
+            })
+            .ok_or(StakedexSdkError::NoRouteFound)
+    }
+
+    /// Same as [`Self::get_amms()`] but only creates the Amms between pools of `mints`,
+    /// excluding quarantined pools, without consuming `self`.
+    ///
+    /// Include [`native_mint::ID`] in `mints` for the SOL <-> LST Amms.
+    /// Use [`accounts_to_update_for_amms()`] for the accounts to fetch to keep them updated.
+    pub fn amms_for_mints(&self, mints: &[Pubkey]) -> Vec<Box<dyn Amm + Send + Sync>> {
+        let mints: HashSet<Pubkey> = mints.iter().copied().collect();
+        let spls = mints
+            .iter()
+            .filter_map(|mint| self.get_unquarantined_spl_pool_by_mint(mint))
+            .cloned()
+            .collect();
+        build_amms(AmmPools {
+            spls,
+            unstakeit: self.unquarantined_pool_of_mints(self.unstakeit.as_ref(), &mints),
+            marinade: self.unquarantined_pool_of_mints(self.marinade.as_ref(), &mints),
+            lido: self.unquarantined_pool_of_mints(self.lido.as_ref(), &mints),
+            with_deposit_sol: mints.contains(&native_mint::ID),
+            bridge_seed_source: None,
+        })
+    }
+
+    fn has_unquarantined_pool_of_mint(&self, mint: &Pubkey) -> bool {
+        self.all_pools()
+            .any(|p| p.staked_sol_mint() == *mint && !self.is_quarantined(&p.main_state_key()))
+    }
+
+    fn unquarantined_pool_of_mints<P: BaseStakePoolAmm + Clone>(
+        &self,
+        pool: &P,
+        mints: &HashSet<Pubkey>,
+    ) -> Option<P> {
+        self.unless_quarantined(pool)
+            .filter(|p| mints.contains(&p.staked_sol_mint()))
+            .cloned()
+    }
+}
+
+/// The minimal set of accounts required to update `amms`,
+/// including the clock sysvar for [`Stakedex::update_clock()`]
+pub fn accounts_to_update_for_amms(amms: &[Box<dyn Amm + Send + Sync>]) -> Vec<Pubkey> {
+    std::iter::once(sysvar::clock::ID)
+        .chain(amms.iter().flat_map(|amm| amm.get_accounts_to_update()))
+        .unique()
+        .collect()
+}
+
+/// Creates all possible Amms from `pools`.
+///
+/// Amms containing the same pool share its state via [`SharedPool`],
+/// which is updated by whichever of them is updated with new account data first.
+pub(crate) fn build_amms(pools: AmmPools) -> Vec<Box<dyn Amm + Send + Sync>> {
+    #[derive(Clone)]
+    enum Stakedex {
+        SplStakePool(SharedPool<SplStakePoolStakedexWithWithdrawSol>),
+        UnstakeIt(SharedPool<UnstakeItStakedexPrefund>),
+        Marinade(SharedPool<MarinadeStakedex>),
+        Lido(SharedPool<LidoStakedex>),
+    }
+
+    let AmmPools {
+        spls,
+        unstakeit,
+        marinade,
+        lido,
+        with_deposit_sol,
+        bridge_seed_source,
+    } = pools;
+
+    // a pool added more than once would otherwise create Amms with duplicate keys
+    // that don't share state
+    let stakedexes: Vec<Stakedex> = spls
+        .into_iter()
+        .unique_by(|spl| spl.inner.stake_pool_addr)
+        .map(|spl| Stakedex::SplStakePool(SharedPool::new(spl)))
+        .chain(unstakeit.map(|p| Stakedex::UnstakeIt(SharedPool::new(p))))
+        .chain(marinade.map(|p| Stakedex::Marinade(SharedPool::new(p))))
+        .chain(lido.map(|p| Stakedex::Lido(SharedPool::new(p))))
+        .collect();
+
+    let mut amm_keys = HashSet::new();
+    let mut amms: Vec<Box<dyn Amm + Send + Sync>> = Vec::new();
+    let mut add_amm_if_new_key = |amm: Box<dyn Amm + Send + Sync>| {
+        let amm_key = amm.key();
+        if !amm_keys.contains(&amm_key) {
+            amm_keys.insert(amm_key);
+            amms.push(amm);
+        }
+    };
+
+    for stakedex in stakedexes.iter().filter(|_| with_deposit_sol) {
+        let amm: Box<dyn Amm + Send + Sync> = match stakedex {
+            Stakedex::SplStakePool(spl_stake_pool) => {
+                Box::new(DepositWithdrawSolWrapper(spl_stake_pool.clone()))
+            }
+            Stakedex::Marinade(marinade) => Box::new(DepositSolWrapper(marinade.clone())),
+            // non-DepositSol
+            Stakedex::UnstakeIt(_) => continue,
+            Stakedex::Lido(_) => continue,
+        };
+        add_amm_if_new_key(amm);
+    }
+
+    // SplStakePool WithdrawStake + DepositStake
+    // UnstakeIt DepositStake
+    // Marinade DepositStake
+    // Lido WithdrawStake
+    macro_rules! with_bridge_seed_source {
+        ($pair:expr) => {{
+            let pair = $pair;
+            match bridge_seed_source.as_ref() {
+                Some(source) => pair.with_bridge_seed_source(source.clone()),
+                None => pair,
+            }
+        }};
+    }
+    for (first_stakedex, second_stakedex) in stakedexes.into_iter().tuple_combinations() {
+        let amm: Box<dyn Amm + Send + Sync> = match (first_stakedex, second_stakedex) {
+            (Stakedex::SplStakePool(p1), Stakedex::SplStakePool(p2)) => {
+                Box::new(with_bridge_seed_source!(TwoWayPoolPair::new(p1, p2)))
+            }
+            match_stakedexes!(SplStakePool, Marinade, withdraw, deposit) => Box::new(
+                with_bridge_seed_source!(OneWayPoolPair::new(withdraw, deposit)),
+            ),
+            match_stakedexes!(SplStakePool, UnstakeIt, withdraw, deposit) => Box::new(
+                with_bridge_seed_source!(OneWayPoolPair::new(withdraw, deposit)),
+            ),
+            match_stakedexes!(Lido, SplStakePool, withdraw, deposit) => Box::new(
+                with_bridge_seed_source!(OneWayPoolPair::new(withdraw, deposit)),
+            ),
+            match_stakedexes!(Lido, UnstakeIt, withdraw, deposit) => Box::new(
+                with_bridge_seed_source!(OneWayPoolPair::new(withdraw, deposit)),
+            ),
+            match_stakedexes!(Lido, Marinade, withdraw, deposit) => Box::new(
+                with_bridge_seed_source!(OneWayPoolPair::new(withdraw, deposit)),
+            ),
+            match_stakedexes!(Marinade, UnstakeIt, _, _) => continue, // Cannot do anything with those two
+            match_same_stakedex!(UnstakeIt)
+            | match_same_stakedex!(Marinade)
+            | match_same_stakedex!(Lido) => continue, // Invalid if found
+        };
+        add_amm_if_new_key(amm);
+    }
+
+    amms
+}
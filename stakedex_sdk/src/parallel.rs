@@ -0,0 +1,73 @@
+//! Runs independent per-pool or per-pair work in parallel if the `rayon` feature is enabled.
+//!
+//! Results are always collected in input order so that outputs and errors are identical
+//! to running sequentially.
+
+use std::sync::Arc;
+
+use jupiter_amm_interface::QuoteParams;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use stakedex_sdk_common::{BaseStakePoolAmm, StakedexSdkError};
+
+use crate::{make_mut_unless_excluded, BestRoute, Stakedex};
+
+/// A pool that can be updated from another thread
+pub(crate) type SendPool<'a> = &'a mut (dyn BaseStakePoolAmm + Send);
+
+#[cfg(feature = "rayon")]
+pub(crate) fn map_mut<T: Send, R: Send>(
+    items: &mut [T],
+    f: impl Fn(&mut T) -> R + Send + Sync,
+) -> Vec<R> {
+    items.par_iter_mut().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map_mut<T, R>(items: &mut [T], f: impl Fn(&mut T) -> R) -> Vec<R> {
+    items.iter_mut().map(f).collect()
+}
+
+#[cfg(feature = "rayon")]
+pub(crate) fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Send + Sync) -> Vec<R> {
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map<T, R>(items: &[T], f: impl Fn(&T) -> R) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
+impl Stakedex {
+    /// Same as [`Self::all_pools_mut()`] but collected for [`map_mut()`]
+    pub(crate) fn all_pools_mut_send(&mut self) -> Vec<SendPool<'_>> {
+        let Self {
+            spls,
+            unstakeit,
+            marinade,
+            lido,
+            excluded,
+            ..
+        } = self;
+        spls.iter_mut()
+            .map(|spl| Arc::make_mut(spl) as SendPool)
+            .chain(make_mut_unless_excluded(unstakeit, excluded).map(|p| p as SendPool))
+            .chain(make_mut_unless_excluded(marinade, excluded).map(|p| p as SendPool))
+            .chain(make_mut_unless_excluded(lido, excluded).map(|p| p as SendPool))
+            .collect()
+    }
+
+    /// [`Self::quote_best()`] for each of `quote_params`, e.g. to refresh the quotes of
+    /// many mint pairs after an update.
+    ///
+    /// Quotes are evaluated in parallel if the `rayon` feature is enabled.
+    /// Results are in the same order as `quote_params`.
+    pub fn quote_best_batch(
+        &self,
+        quote_params: &[QuoteParams],
+    ) -> Vec<Result<BestRoute, StakedexSdkError>> {
+        map(quote_params, |qp| {
+            self.quote_best(&qp.input_mint, &qp.output_mint, qp.amount, qp.swap_mode)
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::{mpsc, Arc};
+
+use solana_sdk::pubkey::Pubkey;
+use stakedex_sdk_common::{
+    diff_pool_params, BaseStakePoolAmm, PoolParam, PoolParamValue, PoolReadiness,
+};
+
+use crate::Stakedex;
+
+/// A change of one of a pool's [`PoolParam`]s, detected on update
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolParamChange {
+    pub main_state_key: Pubkey,
+
+    pub label: String,
+
+    pub param: PoolParam,
+
+    pub old: PoolParamValue,
+
+    pub new: PoolParamValue,
+}
+
+/// Called with each [`PoolParamChange`], see [`Stakedex::set_param_change_listener()`]
+pub type PoolParamChangeListener = Arc<dyn Fn(&PoolParamChange) + Send + Sync>;
+
+/// The params of `pool` before it is updated, `None` if there is no listener
+/// or the pool has not been fully fetched yet, since initializing it is not a change
+pub(crate) fn params_before_update(
+    listener: Option<&PoolParamChangeListener>,
+    pool: &dyn BaseStakePoolAmm,
+) -> Option<Vec<(PoolParam, PoolParamValue)>> {
+    listener?;
+    if pool.readiness() == PoolReadiness::MissingAuxAccounts {
+        return None;
+    }
+    Some(pool.params())
+}
+
+/// Calls `listener` with the changes of `pool`'s params since [`params_before_update()`]
+pub(crate) fn emit_param_changes(
+    listener: Option<&PoolParamChangeListener>,
+    pool: &dyn BaseStakePoolAmm,
+    before: Option<Vec<(PoolParam, PoolParamValue)>>,
+) {
+    let (Some(listener), Some(before)) = (listener, before) else {
+        return;
+    };
+    let after = pool.params();
+    for (param, old, new) in diff_pool_params(&before, &after) {
+        listener(&PoolParamChange {
+            main_state_key: pool.main_state_key(),
+            label: pool.stake_pool_label().to_owned(),
+            param,
+            old,
+            new,
+        });
+    }
+}
+
+impl Stakedex {
+    /// Calls `listener` with each change of a pool's [`PoolParam`]s, e.g. fees, deposit caps,
+    /// authorities, preferred validators or Marinade getting paused, on [`Self::update()`]
+    /// and [`Self::update_accounts()`]. Pools' params are only compared while a listener is set.
+    ///
+    /// Changes are emitted on the updating thread after the pools are updated, in pool order.
+    /// The first update of a pool after initialization does not emit changes.
+    /// Clones of `self` share the listener.
+    pub fn set_param_change_listener(&mut self, listener: Option<PoolParamChangeListener>) {
+        self.param_change_listener = listener;
+    }
+
+    /// Same as [`Self::set_param_change_listener()`], but sends the changes to the returned receiver,
+    /// replacing any listener already set
+    pub fn param_change_receiver(&mut self) -> mpsc::Receiver<PoolParamChange> {
+        let (tx, rx) = mpsc::channel();
+        self.set_param_change_listener(Some(Arc::new(move |change: &PoolParamChange| {
+            // receiver dropped, nobody is interested anymore
+            let _ = tx.send(change.clone());
+        })));
+        rx
+    }
+}
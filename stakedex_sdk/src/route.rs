@@ -0,0 +1,595 @@
+use anyhow::{anyhow, Result};
+use jupiter_amm_interface::{Quote, QuoteParams, SwapMode, SwapParams};
+use rand::RngCore;
+use rust_decimal::{
+    prelude::{FromPrimitive, Zero},
+    Decimal,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::{instruction::close_account, native_mint};
+use stakedex_jup_interface::{
+    approx_fees_charged_out_token, manual_concat_get_account_metas_for_quote,
+    prefund_get_account_metas_for_quote, AccountExistence, BridgeSeedAllocator, PoolPairQuote,
+};
+use stakedex_sdk_common::{
+    DepositStake, DepositStakeQuote, StakedexSdkError, StaleQuoteErr, WithdrawStake,
+    WithdrawStakeQuote,
+};
+
+use crate::Stakedex;
+
+/// The different types of swaps that the stakedex program can perform
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RouteKind {
+    /// SOL -> LST via the output pool's DepositSol
+    StakeWrappedSol,
+
+    /// LST -> SOL via the input pool's WithdrawSol
+    WithdrawWrappedSol,
+
+    /// LST -> LST or LST -> SOL via the input pool's WithdrawStake and the output pool's DepositStake,
+    /// with the bridge stake account prefunded by unstake.it.
+    ///
+    /// For LST -> SOL, the withdrawn stake is deposited into unstake.it
+    PrefundSwapViaStake,
+
+    /// stake account -> LST or SOL via the output pool's DepositStake.
+    ///
+    /// `input_mint` is the voter pubkey of the stake account to be deposited.
+    /// Never returned by [`Stakedex::route_kinds()`] since `input_mint` is not a token mint.
+    DepositStake,
+
+    /// LST -> LST via the input pool's WithdrawSol followed by the output pool's DepositSol,
+    /// with the user's wSOL associated token account holding the intermediate wSOL.
+    /// The account is closed at the end of the route, unwrapping any remaining wSOL.
+    ///
+    /// Useful when PrefundSwapViaStake is unavailable, e.g. no shared validator
+    /// or insufficient unstake.it liquidity for the prefund.
+    WithdrawThenStakeWrappedSol,
+}
+
+/// A quote for a specific [`RouteKind`].
+///
+/// Also holds the intermediate results the quote was computed from
+/// so that [`Stakedex::build_ixs()`] executes the exact quoted route.
+#[derive(Clone, Debug)]
+pub struct RouteQuote {
+    pub kind: RouteKind,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub quote: Quote,
+    epoch: u64,
+    bound: BoundRoute,
+}
+
+#[derive(Clone, Debug)]
+enum BoundRoute {
+    StakeWrappedSol,
+    WithdrawWrappedSol,
+    PrefundSwapViaStake(PoolPairQuote),
+    DepositStake(DepositStakeQuote),
+    WithdrawThenStakeWrappedSol {
+        /// wSOL received from the WithdrawWrappedSol hop and deposited in the StakeWrappedSol hop
+        intermediate_lamports: u64,
+    },
+}
+
+impl RouteQuote {
+    /// The epoch the quote was computed in
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The vote account of the stake that the route goes through,
+    /// `None` for SOL routes
+    pub fn voter(&self) -> Option<Pubkey> {
+        match &self.bound {
+            BoundRoute::StakeWrappedSol
+            | BoundRoute::WithdrawWrappedSol
+            | BoundRoute::WithdrawThenStakeWrappedSol { .. } => None,
+            BoundRoute::PrefundSwapViaStake(ppq) => Some(ppq.voter()),
+            BoundRoute::DepositStake(dsq) => Some(dsq.voter),
+        }
+    }
+
+    /// Total lamports of the slumdog stake that repays the prefund flash loan,
+    /// `None` for non-prefund routes
+    pub fn slumdog_target_lamports(&self) -> Option<u64> {
+        match &self.bound {
+            BoundRoute::PrefundSwapViaStake(ppq) => Some(ppq.slumdog_target_lamports),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BestRoute {
+    pub best: RouteQuote,
+
+    /// All routes that were successfully quoted, including `best`
+    pub candidates: Vec<RouteQuote>,
+}
+
+impl Stakedex {
+    /// Returns all token route kinds that the loaded pools can possibly perform for `input_mint -> output_mint`.
+    ///
+    /// This only checks that the required pools exist, not that they can currently service the swap.
+    pub fn route_kinds(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> Vec<RouteKind> {
+        let mut res = Vec::new();
+        if input_mint == output_mint {
+            return res;
+        }
+        if *input_mint == native_mint::ID {
+            if self.get_deposit_sol_pool(output_mint).is_some() {
+                res.push(RouteKind::StakeWrappedSol);
+            }
+            return res;
+        }
+        if *output_mint == native_mint::ID && self.get_withdraw_sol_pool(input_mint).is_some() {
+            res.push(RouteKind::WithdrawWrappedSol);
+        }
+        if self.get_withdraw_stake_pool(input_mint).is_some()
+            && self.get_deposit_stake_pool(output_mint).is_some()
+            && self.check_prefund_available().is_ok()
+        {
+            res.push(RouteKind::PrefundSwapViaStake);
+        }
+        if *output_mint != native_mint::ID
+            && self.get_withdraw_sol_pool(input_mint).is_some()
+            && self.get_deposit_sol_pool(output_mint).is_some()
+        {
+            res.push(RouteKind::WithdrawThenStakeWrappedSol);
+        }
+        res
+    }
+
+    pub fn quote_route(
+        &self,
+        kind: RouteKind,
+        quote_params: &QuoteParams,
+    ) -> Result<RouteQuote, StakedexSdkError> {
+        self.quote_route_for_voters(kind, quote_params, &|_| true)
+    }
+
+    /// [`Self::quote_route()`], but PrefundSwapViaStake routes only withdraw stake
+    /// delegated to vote accounts that `voter_filter` returns true for
+    pub(crate) fn quote_route_for_voters(
+        &self,
+        kind: RouteKind,
+        quote_params: &QuoteParams,
+        voter_filter: &dyn Fn(&Pubkey) -> bool,
+    ) -> Result<RouteQuote, StakedexSdkError> {
+        let epoch = self.curr_epoch();
+        let (quote, bound) = match kind {
+            RouteKind::StakeWrappedSol => (
+                self.quote_stake_wrapped_sol(quote_params)?,
+                BoundRoute::StakeWrappedSol,
+            ),
+            RouteKind::WithdrawWrappedSol => (
+                self.quote_withdraw_wrapped_sol(quote_params)?,
+                BoundRoute::WithdrawWrappedSol,
+            ),
+            RouteKind::PrefundSwapViaStake => {
+                let ppq = self.quote_swap_via_stake_detailed(quote_params, voter_filter)?;
+                (ppq.quote.clone(), BoundRoute::PrefundSwapViaStake(ppq))
+            }
+            RouteKind::DepositStake => {
+                let (quote, dsq) = self.quote_deposit_stake_detailed(quote_params)?;
+                (quote, BoundRoute::DepositStake(dsq))
+            }
+            RouteKind::WithdrawThenStakeWrappedSol => {
+                let (withdraw_quote, stake_quote) =
+                    self.quote_withdraw_then_stake_wrapped_sol_hops(quote_params)?;
+                (
+                    compose_two_hop_quote(&withdraw_quote, &stake_quote)?,
+                    BoundRoute::WithdrawThenStakeWrappedSol {
+                        intermediate_lamports: withdraw_quote.out_amount,
+                    },
+                )
+            }
+        };
+        Ok(RouteQuote {
+            kind,
+            input_mint: quote_params.input_mint,
+            output_mint: quote_params.output_mint,
+            quote,
+            epoch,
+            bound,
+        })
+    }
+
+    /// Quotes all applicable route kinds for `input_mint -> output_mint` and picks
+    /// the one with the highest `out_amount` for [`SwapMode::ExactIn`]
+    /// or the lowest `in_amount` for [`SwapMode::ExactOut`].
+    ///
+    /// Returns the err of the first route kind if no route kinds could be quoted,
+    /// or [`StakedexSdkError::NoRouteFound`] if there are no applicable route kinds.
+    pub fn quote_best(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        amount: u64,
+        swap_mode: SwapMode,
+    ) -> Result<BestRoute, StakedexSdkError> {
+        let quote_params = QuoteParams {
+            amount,
+            input_mint: *input_mint,
+            output_mint: *output_mint,
+            swap_mode,
+        };
+        let mut first_err = None;
+        let candidates: Vec<RouteQuote> = self
+            .route_kinds(input_mint, output_mint)
+            .into_iter()
+            .filter_map(|kind| match self.quote_route(kind, &quote_params) {
+                Ok(rq) => Some(rq),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                    None
+                }
+            })
+            .collect();
+        // on ties, prefer the route kind that comes first
+        let best = candidates
+            .iter()
+            .reduce(|best, rq| {
+                let is_better = match swap_mode {
+                    SwapMode::ExactIn => rq.quote.out_amount > best.quote.out_amount,
+                    SwapMode::ExactOut => rq.quote.in_amount < best.quote.in_amount,
+                };
+                if is_better {
+                    rq
+                } else {
+                    best
+                }
+            })
+            .cloned();
+        match best {
+            Some(best) => Ok(BestRoute { best, candidates }),
+            None => Err(first_err.unwrap_or(StakedexSdkError::NoRouteFound)),
+        }
+    }
+
+    /// Creates the instructions to execute a quoted route.
+    ///
+    /// PrefundSwapViaStake routes use a random bridge stake seed without checking if
+    /// its accounts already exist, use [`Self::build_ixs_with_allocator()`] to avoid collisions.
+    ///
+    /// Errs with [`StakedexSdkError::StaleQuote`] if `route` can no longer be executed as quoted,
+    /// in which case it should be requoted.
+    pub fn build_ixs(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        self.build_ixs_with_bridge_stake_seed(route, swap_params, rand::random())
+    }
+
+    /// Same as [`Self::build_ixs()`], but PrefundSwapViaStake routes use
+    /// a bridge stake seed from `allocator` that does not collide with existing accounts
+    pub fn build_ixs_with_allocator<O: AccountExistence, R: RngCore>(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        allocator: &mut BridgeSeedAllocator<O, R>,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        let bridge_stake_seed = match route.kind {
+            RouteKind::PrefundSwapViaStake => {
+                allocator.allocate(&swap_params.token_transfer_authority)?
+            }
+            _ => 0,
+        };
+        self.build_ixs_with_bridge_stake_seed(route, swap_params, bridge_stake_seed)
+    }
+
+    /// `bridge_stake_seed` is ignored for non-PrefundSwapViaStake routes
+    pub fn build_ixs_with_bridge_stake_seed(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        bridge_stake_seed: u32,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        self.check_route_params(route, swap_params)?;
+        let ix = match &route.bound {
+            BoundRoute::StakeWrappedSol => {
+                let deposit_to = self
+                    .get_deposit_sol_pool(&route.output_mint)
+                    .ok_or(StaleQuoteErr::RouteInvalidated)?;
+                if !deposit_to.can_accept_sol_deposits() {
+                    return Err(StaleQuoteErr::RouteInvalidated.into());
+                }
+                self.stake_wrapped_sol_ix(swap_params)
+            }
+            BoundRoute::WithdrawWrappedSol => {
+                let withdraw_from = self
+                    .get_withdraw_sol_pool(&route.input_mint)
+                    .ok_or(StaleQuoteErr::RouteInvalidated)?;
+                if withdraw_from
+                    .get_withdraw_sol_quote(swap_params.in_amount)
+                    .is_err()
+                {
+                    return Err(StaleQuoteErr::RouteInvalidated.into());
+                }
+                self.withdraw_wrapped_sol_ix(swap_params)
+            }
+            BoundRoute::PrefundSwapViaStake(ppq) => {
+                let (withdraw_from, deposit_to) = self.bound_pool_pair(route)?;
+                let metas = prefund_get_account_metas_for_quote(
+                    swap_params,
+                    &self.prefund_repay_params(),
+                    withdraw_from,
+                    deposit_to,
+                    ppq,
+                    bridge_stake_seed,
+                )?;
+                Self::prefund_swap_via_stake_ix_from_metas(swap_params, bridge_stake_seed, metas)
+            }
+            BoundRoute::DepositStake(dsq) => {
+                let deposit_to = self
+                    .get_deposit_stake_pool(&route.output_mint)
+                    .ok_or(StaleQuoteErr::RouteInvalidated)?;
+                let wsq = WithdrawStakeQuote::from_lamports_and_voter(
+                    swap_params.in_amount,
+                    swap_params.source_mint,
+                );
+                match deposit_to.get_deposit_stake_quote(wsq) {
+                    Ok(curr) if !curr.is_zero_out() && curr.voter == dsq.voter => (),
+                    _ => return Err(StaleQuoteErr::RouteInvalidated.into()),
+                }
+                Self::deposit_stake_ix_from_dsq(swap_params, deposit_to, dsq)
+            }
+            BoundRoute::WithdrawThenStakeWrappedSol {
+                intermediate_lamports,
+            } => {
+                return self.withdraw_then_stake_wrapped_sol_ixs(
+                    route,
+                    swap_params,
+                    *intermediate_lamports,
+                )
+            }
+        }?;
+        Ok(vec![ix])
+    }
+
+    /// Same as [`Self::build_ixs()`], but splits PrefundSwapViaStake routes into
+    /// separate PrefundWithdrawStake and DepositStake instructions.
+    pub fn build_manual_concat_ixs(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        self.build_manual_concat_ixs_with_bridge_stake_seed(route, swap_params, rand::random())
+    }
+
+    /// Same as [`Self::build_manual_concat_ixs()`], but with
+    /// a bridge stake seed from `allocator`, see [`Self::build_ixs_with_allocator()`]
+    pub fn build_manual_concat_ixs_with_allocator<O: AccountExistence, R: RngCore>(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        allocator: &mut BridgeSeedAllocator<O, R>,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        let bridge_stake_seed = match route.kind {
+            RouteKind::PrefundSwapViaStake => {
+                allocator.allocate(&swap_params.token_transfer_authority)?
+            }
+            _ => 0,
+        };
+        self.build_manual_concat_ixs_with_bridge_stake_seed(route, swap_params, bridge_stake_seed)
+    }
+
+    /// `bridge_stake_seed` is ignored for non-PrefundSwapViaStake routes
+    pub fn build_manual_concat_ixs_with_bridge_stake_seed(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        bridge_stake_seed: u32,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        let ppq = match &route.bound {
+            BoundRoute::PrefundSwapViaStake(ppq) => ppq,
+            _ => {
+                return self.build_ixs_with_bridge_stake_seed(route, swap_params, bridge_stake_seed)
+            }
+        };
+        self.check_route_params(route, swap_params)?;
+        let (withdraw_from, deposit_to) = self.bound_pool_pair(route)?;
+        let metas = manual_concat_get_account_metas_for_quote(
+            swap_params,
+            &self.prefund_repay_params(),
+            withdraw_from,
+            deposit_to,
+            ppq,
+            bridge_stake_seed,
+        )?;
+        Ok(Self::manual_concat_prefund_swap_via_stake_ixs_from_metas(
+            swap_params,
+            bridge_stake_seed,
+            metas,
+        )?
+        .into())
+    }
+
+    /// Returns the (WithdrawWrappedSol, StakeWrappedSol) quotes of the 2 hops
+    fn quote_withdraw_then_stake_wrapped_sol_hops(
+        &self,
+        quote_params: &QuoteParams,
+    ) -> Result<(Quote, Quote), StakedexSdkError> {
+        let withdraw_quote = match quote_params.swap_mode {
+            SwapMode::ExactIn => self.quote_withdraw_wrapped_sol(&QuoteParams {
+                output_mint: native_mint::ID,
+                ..*quote_params
+            })?,
+            SwapMode::ExactOut => {
+                let stake_quote = self.quote_stake_wrapped_sol(&QuoteParams {
+                    input_mint: native_mint::ID,
+                    ..*quote_params
+                })?;
+                self.quote_withdraw_wrapped_sol(&QuoteParams {
+                    amount: stake_quote.in_amount,
+                    output_mint: native_mint::ID,
+                    ..*quote_params
+                })?
+            }
+        };
+        // for ExactOut, the withdrawn wSOL may be slightly more than required,
+        // so quote the stake hop with all of it
+        let stake_quote = self.quote_stake_wrapped_sol(&QuoteParams {
+            amount: withdraw_quote.out_amount,
+            input_mint: native_mint::ID,
+            swap_mode: SwapMode::ExactIn,
+            ..*quote_params
+        })?;
+        Ok((withdraw_quote, stake_quote))
+    }
+
+    /// Creates the user's wSOL ATA if it doesn't exist, withdraws into it,
+    /// stakes `intermediate_lamports` of it, then closes it.
+    ///
+    /// Closing returns the ATA's rent and any remaining wSOL to the user as SOL,
+    /// so a wSOL balance the user held in the ATA before the swap ends up unwrapped.
+    fn withdraw_then_stake_wrapped_sol_ixs(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        intermediate_lamports: u64,
+    ) -> Result<Vec<Instruction>, StakedexSdkError> {
+        let withdraw_from = self
+            .get_withdraw_sol_pool(&route.input_mint)
+            .ok_or(StaleQuoteErr::RouteInvalidated)?;
+        match withdraw_from.get_withdraw_sol_quote(swap_params.in_amount) {
+            Ok(wsq) if withdraw_from.convert_quote(wsq).out_amount >= intermediate_lamports => (),
+            _ => return Err(StaleQuoteErr::RouteInvalidated.into()),
+        }
+        let deposit_to = self
+            .get_deposit_sol_pool(&route.output_mint)
+            .ok_or(StaleQuoteErr::RouteInvalidated)?;
+        if !deposit_to.can_accept_sol_deposits() {
+            return Err(StaleQuoteErr::RouteInvalidated.into());
+        }
+
+        let user = swap_params.token_transfer_authority;
+        let wsol_ata = get_associated_token_address(&user, &native_mint::ID);
+        let create_wsol_ata_ix = create_associated_token_account_idempotent(
+            &user,
+            &user,
+            &native_mint::ID,
+            &spl_token::ID,
+        );
+        let withdraw_ix = self.withdraw_wrapped_sol_ix(&SwapParams {
+            jupiter_program_id: swap_params.jupiter_program_id,
+            in_amount: swap_params.in_amount,
+            out_amount: intermediate_lamports,
+            source_mint: swap_params.source_mint,
+            destination_mint: native_mint::ID,
+            source_token_account: swap_params.source_token_account,
+            destination_token_account: wsol_ata,
+            token_transfer_authority: user,
+            open_order_address: swap_params.open_order_address,
+            quote_mint_to_referrer: swap_params.quote_mint_to_referrer,
+            missing_dynamic_accounts_as_default: swap_params.missing_dynamic_accounts_as_default,
+            swap_mode: SwapMode::ExactIn,
+        })?;
+        let stake_ix = self.stake_wrapped_sol_ix(&SwapParams {
+            jupiter_program_id: swap_params.jupiter_program_id,
+            in_amount: intermediate_lamports,
+            out_amount: swap_params.out_amount,
+            source_mint: native_mint::ID,
+            destination_mint: swap_params.destination_mint,
+            source_token_account: wsol_ata,
+            destination_token_account: swap_params.destination_token_account,
+            token_transfer_authority: user,
+            open_order_address: swap_params.open_order_address,
+            quote_mint_to_referrer: swap_params.quote_mint_to_referrer,
+            missing_dynamic_accounts_as_default: swap_params.missing_dynamic_accounts_as_default,
+            swap_mode: SwapMode::ExactIn,
+        })?;
+        let close_wsol_ata_ix =
+            close_account(&spl_token::ID, &wsol_ata, &user, &user, &[]).map_err(|e| anyhow!(e))?;
+        Ok(vec![
+            create_wsol_ata_ix,
+            withdraw_ix,
+            stake_ix,
+            close_wsol_ata_ix,
+        ])
+    }
+
+    fn check_route_params(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+    ) -> Result<(), StakedexSdkError> {
+        if route.input_mint != swap_params.source_mint
+            || route.output_mint != swap_params.destination_mint
+        {
+            return Err(StakedexSdkError::Other(anyhow!(
+                "route {} -> {} does not match swap {} -> {}",
+                route.input_mint,
+                route.output_mint,
+                swap_params.source_mint,
+                swap_params.destination_mint
+            )));
+        }
+        if swap_params.in_amount != route.quote.in_amount {
+            return Err(StaleQuoteErr::AmountMismatch.into());
+        }
+        if route.epoch != self.curr_epoch() {
+            return Err(StaleQuoteErr::EpochChanged.into());
+        }
+        Ok(())
+    }
+
+    fn bound_pool_pair(
+        &self,
+        route: &RouteQuote,
+    ) -> Result<(&dyn WithdrawStake, &dyn DepositStake), StakedexSdkError> {
+        self.check_prefund_available()?;
+        let withdraw_from = self
+            .get_withdraw_stake_pool(&route.input_mint)
+            .ok_or(StaleQuoteErr::RouteInvalidated)?;
+        let deposit_to = self
+            .get_deposit_stake_pool(&route.output_mint)
+            .ok_or(StaleQuoteErr::RouteInvalidated)?;
+        Ok((withdraw_from, deposit_to))
+    }
+}
+
+/// Composes the quotes of the 2 hops of a WithdrawThenStakeWrappedSol route,
+/// counting all fees in terms of the output mint like [`PoolPairQuote`]
+fn compose_two_hop_quote(
+    withdraw_quote: &Quote,
+    stake_quote: &Quote,
+) -> Result<Quote, StakedexSdkError> {
+    // withdraw_quote's fees include the WithdrawWrappedSol stakedex fee
+    let approx_withdraw_fee_out_token = approx_fees_charged_out_token(
+        stake_quote
+            .out_amount
+            .checked_add(stake_quote.fee_amount)
+            .ok_or(StakedexSdkError::MathOverflow)?,
+        withdraw_quote.fee_amount,
+        withdraw_quote
+            .out_amount
+            .checked_add(withdraw_quote.fee_amount)
+            .ok_or(StakedexSdkError::MathOverflow)?,
+    )?;
+    let fee_amount = stake_quote
+        .fee_amount
+        .checked_add(approx_withdraw_fee_out_token)
+        .ok_or(StakedexSdkError::MathOverflow)?;
+    let out_amount_before_fees = stake_quote
+        .out_amount
+        .checked_add(fee_amount)
+        .ok_or(StakedexSdkError::MathOverflow)?;
+    let fee_pct = Decimal::from_f64((fee_amount as f64) / (out_amount_before_fees as f64))
+        .unwrap_or_else(Decimal::zero);
+    Ok(Quote {
+        in_amount: withdraw_quote.in_amount,
+        out_amount: stake_quote.out_amount,
+        fee_amount,
+        fee_pct,
+        fee_mint: stake_quote.fee_mint,
+        ..Quote::default()
+    })
+}
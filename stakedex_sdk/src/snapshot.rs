@@ -0,0 +1,224 @@
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use stakedex_lido::LidoStakedex;
+use stakedex_marinade::MarinadeStakedex;
+use stakedex_sdk_common::StakedexSdkError;
+use stakedex_spl_stake_pool::SplStakePoolStakedexWithWithdrawSol;
+use stakedex_unstake_it::UnstakeItStakedexPrefund;
+
+use crate::Stakedex;
+
+/// Version of the snapshot format written by [`Stakedex::to_snapshot_bytes()`]
+/// and [`Stakedex::to_snapshot_json()`]. Bumped on every breaking change to any pool's layout.
+pub const STAKEDEX_SNAPSHOT_VERSION: u8 = 3;
+
+/// Borsh layout of a version 3 binary snapshot, after the leading version byte:
+/// (epoch, excluded, spls, unstakeit, marinade, lido)
+type SnapshotV3 = (
+    u64,
+    Vec<Pubkey>,
+    Vec<SplStakePoolStakedexWithWithdrawSol>,
+    UnstakeItStakedexPrefund,
+    MarinadeStakedex,
+    LidoStakedex,
+);
+
+/// JSON snapshot format. Each pool's state is its base64-encoded borsh serialization.
+#[derive(Serialize, Deserialize)]
+struct SnapshotJson {
+    version: u8,
+    epoch: u64,
+    excluded: Vec<String>,
+    spls: Vec<SplSnapshotJson>,
+    unstakeit: String,
+    marinade: String,
+    lido: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SplSnapshotJson {
+    label: String,
+    stake_pool_addr: String,
+    state: String,
+}
+
+fn borsh_base64<T: BorshSerialize>(val: &T) -> Result<String, StakedexSdkError> {
+    Ok(BASE64.encode(borsh::to_vec(val)?))
+}
+
+fn from_borsh_base64<T: BorshDeserialize>(s: &str) -> Result<T, StakedexSdkError> {
+    let bytes = BASE64
+        .decode(s)
+        .map_err(|e| anyhow!("invalid base64 in snapshot: {}", e))?;
+    Ok(T::try_from_slice(&bytes)?)
+}
+
+fn check_version(version: u8) -> Result<(), StakedexSdkError> {
+    if version != STAKEDEX_SNAPSHOT_VERSION {
+        return Err(StakedexSdkError::Other(anyhow!(
+            "unsupported snapshot version {}, expected {}",
+            version,
+            STAKEDEX_SNAPSHOT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+impl Stakedex {
+    /// main_state_keys of the excluded pools, sorted so that snapshots are deterministic
+    fn sorted_excluded(&self) -> Vec<Pubkey> {
+        let mut res: Vec<Pubkey> = self.excluded.iter().copied().collect();
+        res.sort();
+        res
+    }
+
+    /// Serializes the state of all pools, the excluded pools and the current epoch into
+    /// a version byte followed by their borsh serialization.
+    pub fn to_snapshot_bytes(&self) -> Result<Vec<u8>, StakedexSdkError> {
+        let mut res = vec![STAKEDEX_SNAPSHOT_VERSION];
+        // same layout as SnapshotV3
+        (
+            self.curr_epoch(),
+            self.sorted_excluded(),
+            self.spls.iter().map(Arc::as_ref).collect::<Vec<_>>(),
+            self.unstakeit.as_ref(),
+            self.marinade.as_ref(),
+            self.lido.as_ref(),
+        )
+            .serialize(&mut res)?;
+        Ok(res)
+    }
+
+    /// Restores a [`Stakedex`] from the output of [`Self::to_snapshot_bytes()`]
+    /// without needing to fetch any accounts.
+    ///
+    /// Quotes from the restored instance are equal to those of the original
+    /// at the time of the snapshot.
+    pub fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, StakedexSdkError> {
+        let (version, data) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow!("empty snapshot"))?;
+        check_version(*version)?;
+        let (epoch, excluded, spls, unstakeit, marinade, lido) = SnapshotV3::try_from_slice(data)?;
+        Ok(Self::from_snapshot_parts(
+            epoch,
+            excluded.into_iter().collect(),
+            spls,
+            unstakeit,
+            marinade,
+            lido,
+        ))
+    }
+
+    /// Same as [`Self::to_snapshot_bytes()`], but as JSON with each pool's state
+    /// base64-encoded so that the list of pools remains human-readable.
+    pub fn to_snapshot_json(&self) -> Result<String, StakedexSdkError> {
+        let spls = self
+            .spls
+            .iter()
+            .map(|spl| {
+                Ok(SplSnapshotJson {
+                    label: spl.inner.stake_pool_label.clone(),
+                    stake_pool_addr: spl.inner.stake_pool_addr.to_string(),
+                    state: borsh_base64(spl.as_ref())?,
+                })
+            })
+            .collect::<Result<_, StakedexSdkError>>()?;
+        let snapshot = SnapshotJson {
+            version: STAKEDEX_SNAPSHOT_VERSION,
+            epoch: self.curr_epoch(),
+            excluded: self
+                .sorted_excluded()
+                .iter()
+                .map(Pubkey::to_string)
+                .collect(),
+            spls,
+            unstakeit: borsh_base64(self.unstakeit.as_ref())?,
+            marinade: borsh_base64(self.marinade.as_ref())?,
+            lido: borsh_base64(self.lido.as_ref())?,
+        };
+        serde_json::to_string(&snapshot).map_err(|e| StakedexSdkError::Other(e.into()))
+    }
+
+    /// Restores a [`Stakedex`] from the output of [`Self::to_snapshot_json()`],
+    /// see [`Self::from_snapshot_bytes()`]
+    pub fn from_snapshot_json(json: &str) -> Result<Self, StakedexSdkError> {
+        let SnapshotJson {
+            version,
+            epoch,
+            excluded,
+            spls,
+            unstakeit,
+            marinade,
+            lido,
+        } = serde_json::from_str(json).map_err(|e| StakedexSdkError::Other(e.into()))?;
+        check_version(version)?;
+        let excluded = excluded
+            .iter()
+            .map(|key| {
+                Ok(Pubkey::from_str(key)
+                    .map_err(|e| anyhow!("invalid excluded pool in snapshot: {}", e))?)
+            })
+            .collect::<Result<_, StakedexSdkError>>()?;
+        let spls = spls
+            .iter()
+            .map(|spl_json| {
+                let spl: SplStakePoolStakedexWithWithdrawSol = from_borsh_base64(&spl_json.state)?;
+                let addr = Pubkey::from_str(&spl_json.stake_pool_addr)
+                    .map_err(|e| anyhow!("invalid stake pool addr in snapshot: {}", e))?;
+                if addr != spl.inner.stake_pool_addr {
+                    return Err(StakedexSdkError::Other(anyhow!(
+                        "snapshot stake pool addr {} does not match state {}",
+                        addr,
+                        spl.inner.stake_pool_addr
+                    )));
+                }
+                Ok(spl)
+            })
+            .collect::<Result<_, StakedexSdkError>>()?;
+        Ok(Self::from_snapshot_parts(
+            epoch,
+            excluded,
+            spls,
+            from_borsh_base64(&unstakeit)?,
+            from_borsh_base64(&marinade)?,
+            from_borsh_base64(&lido)?,
+        ))
+    }
+
+    /// Relinks all pools to a new shared epoch
+    fn from_snapshot_parts(
+        epoch: u64,
+        excluded: HashSet<Pubkey>,
+        mut spls: Vec<SplStakePoolStakedexWithWithdrawSol>,
+        unstakeit: UnstakeItStakedexPrefund,
+        marinade: MarinadeStakedex,
+        mut lido: LidoStakedex,
+    ) -> Self {
+        let curr_epoch = Arc::new(AtomicU64::new(epoch));
+        for spl in spls.iter_mut() {
+            spl.inner.curr_epoch = curr_epoch.clone();
+        }
+        lido.set_curr_epoch(curr_epoch.clone());
+        let mut res = Self {
+            spls: spls.into_iter().map(Arc::new).collect(),
+            unstakeit: Arc::new(unstakeit),
+            marinade: Arc::new(marinade),
+            lido: Arc::new(lido),
+            curr_epoch,
+            excluded,
+            ..Default::default()
+        };
+        res.rebuild_spl_indices();
+        res
+    }
+}
@@ -0,0 +1,187 @@
+use jupiter_amm_interface::{QuoteParams, SwapMode, SwapParams};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_token::native_mint;
+use stakedex_sdk_common::{StakedexSdkError, StaleQuoteErr};
+
+use crate::{RouteKind, RouteQuote, Stakedex};
+
+/// Default number of parts [`Stakedex::quote_split()`] divides the input amount into
+pub const DEFAULT_SPLIT_PARTS: u64 = 20;
+
+/// An input amount divided across multiple routes
+#[derive(Clone, Debug)]
+pub struct SplitQuote {
+    /// At most one leg per [`crate::RouteKind`] and vote account, each with a nonzero `in_amount`.
+    /// No two legs draw on the same pool reserves, see [`Stakedex::quote_split()`]
+    pub legs: Vec<RouteQuote>,
+
+    /// Sum of all legs' `in_amount`
+    pub in_amount: u64,
+
+    /// Sum of all legs' `out_amount`
+    pub out_amount: u64,
+}
+
+impl Stakedex {
+    /// Divides `amount` of `input_mint` across the routes available for
+    /// `input_mint -> output_mint` to maximise total output, for trades large enough to
+    /// saturate a single route, e.g. rising unstake.it fees, SPL WithdrawSol reserve limits.
+    ///
+    /// `amount` is split into `parts` equal parts, each greedily allocated to whichever
+    /// existing leg or new leg has the highest marginal output for it.
+    /// PrefundSwapViaStake legs each withdraw stake delegated to a different vote account.
+    ///
+    /// Each leg is quoted against the current pool state, so a new leg is only added if it
+    /// does not draw on the same reserves as an existing leg:
+    /// the input pool's SOL reserves, the input pool's stake delegated to a vote account,
+    /// or unstake.it's liquidity for stake deposited into it.
+    /// Legs may still receive slightly less than quoted when executed together
+    /// since each leg's fees change the pools' exchange rates.
+    pub fn quote_split(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+        amount: u64,
+        parts: u64,
+    ) -> Result<SplitQuote, StakedexSdkError> {
+        let kinds = self.route_kinds(input_mint, output_mint);
+        if kinds.is_empty() {
+            return Err(StakedexSdkError::NoRouteFound);
+        }
+        let parts = parts.clamp(1, amount.max(1));
+        let part_amount = amount / parts;
+        let quote_params = |amount| QuoteParams {
+            amount,
+            input_mint: *input_mint,
+            output_mint: *output_mint,
+            swap_mode: SwapMode::ExactIn,
+        };
+        let mut legs: Vec<RouteQuote> = Vec::new();
+        let mut first_err = None;
+
+        for part in 0..parts {
+            // last part takes the remainder
+            let part_amount = if part == parts - 1 {
+                amount - part_amount * (parts - 1)
+            } else {
+                part_amount
+            };
+            // (index of the leg to grow or None for a new leg, marginal out, quote)
+            let mut best: Option<(Option<usize>, u64, RouteQuote)> = None;
+            // on ties, prefer growing an existing leg, then the route kind that comes first
+            let mut consider = |leg_idx: Option<usize>, marginal_out: u64, rq: RouteQuote| {
+                if best
+                    .as_ref()
+                    .map_or(true, |(_, best_marginal, _)| marginal_out > *best_marginal)
+                {
+                    best = Some((leg_idx, marginal_out, rq));
+                }
+            };
+
+            for (i, leg) in legs.iter().enumerate() {
+                let voter = leg.voter();
+                match self.quote_route_for_voters(
+                    leg.kind,
+                    &quote_params(leg.quote.in_amount + part_amount),
+                    &|v| Some(*v) == voter,
+                ) {
+                    Ok(rq) => {
+                        let marginal_out = rq.quote.out_amount.saturating_sub(leg.quote.out_amount);
+                        consider(Some(i), marginal_out, rq);
+                    }
+                    Err(e) => {
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+            for kind in kinds.iter() {
+                // legs without a voter are grown instead
+                if *kind != RouteKind::PrefundSwapViaStake && legs.iter().any(|l| l.kind == *kind) {
+                    continue;
+                }
+                match self.quote_route_for_voters(*kind, &quote_params(part_amount), &|v| {
+                    !legs.iter().any(|l| l.voter() == Some(*v))
+                }) {
+                    Ok(rq) if !legs.iter().any(|l| legs_overlap(l, &rq)) => {
+                        consider(None, rq.quote.out_amount, rq);
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+
+            match best {
+                Some((Some(i), _, rq)) => legs[i] = rq,
+                Some((None, _, rq)) => legs.push(rq),
+                None => return Err(first_err.unwrap_or(StakedexSdkError::NoRouteFound)),
+            }
+        }
+
+        let (in_amount, out_amount) = legs.iter().fold((0, 0), |(i, o), rq| {
+            (i + rq.quote.in_amount, o + rq.quote.out_amount)
+        });
+        Ok(SplitQuote {
+            legs,
+            in_amount,
+            out_amount,
+        })
+    }
+
+    /// Creates the instructions of each leg of `split`, in the same order as `split.legs`.
+    ///
+    /// `swap_params` is used for all legs with `in_amount` and `out_amount` replaced
+    /// by the leg's, and its `in_amount` must equal `split.in_amount`.
+    /// Legs can be executed in the same transaction if they fit, or in separate ones.
+    pub fn build_split_ixs(
+        &self,
+        split: &SplitQuote,
+        swap_params: &SwapParams,
+    ) -> Result<Vec<Vec<Instruction>>, StakedexSdkError> {
+        if swap_params.in_amount != split.in_amount {
+            return Err(StaleQuoteErr::AmountMismatch.into());
+        }
+        split
+            .legs
+            .iter()
+            .map(|leg| {
+                self.build_ixs(
+                    leg,
+                    &SwapParams {
+                        jupiter_program_id: swap_params.jupiter_program_id,
+                        in_amount: leg.quote.in_amount,
+                        out_amount: leg.quote.out_amount,
+                        source_mint: swap_params.source_mint,
+                        destination_mint: swap_params.destination_mint,
+                        source_token_account: swap_params.source_token_account,
+                        destination_token_account: swap_params.destination_token_account,
+                        token_transfer_authority: swap_params.token_transfer_authority,
+                        open_order_address: swap_params.open_order_address,
+                        quote_mint_to_referrer: swap_params.quote_mint_to_referrer,
+                        missing_dynamic_accounts_as_default: swap_params
+                            .missing_dynamic_accounts_as_default,
+                        swap_mode: SwapMode::ExactIn,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Whether `a` and `b` draw on the same pool reserves, in which case
+/// quoting them independently overestimates their total output
+fn legs_overlap(a: &RouteQuote, b: &RouteQuote) -> bool {
+    let withdraws_sol = |rq: &RouteQuote| {
+        matches!(
+            rq.kind,
+            RouteKind::WithdrawWrappedSol | RouteKind::WithdrawThenStakeWrappedSol
+        )
+    };
+    let deposits_stake_to_unstakeit = |rq: &RouteQuote| {
+        rq.kind == RouteKind::PrefundSwapViaStake && rq.output_mint == native_mint::ID
+    };
+    (withdraws_sol(a) && withdraws_sol(b))
+        || (a.voter().is_some() && a.voter() == b.voter())
+        || (deposits_stake_to_unstakeit(a) && deposits_stake_to_unstakeit(b))
+}
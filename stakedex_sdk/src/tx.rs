@@ -0,0 +1,141 @@
+use anyhow::anyhow;
+use jupiter_amm_interface::SwapParams;
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0::Message, VersionedMessage},
+    pubkey::Pubkey,
+};
+use stakedex_sdk_common::StakedexSdkError;
+
+use crate::{srlut, RouteQuote, Stakedex, SWAP_VIA_STAKE_COMPUTE_BUDGET_LIMIT};
+
+/// Params for assembling a swap transaction around the router's instructions
+#[derive(Clone, Copy, Debug)]
+pub struct SwapTxParams<'a> {
+    /// Pays for tx fees, always the first signer
+    pub payer: Pubkey,
+
+    pub recent_blockhash: Hash,
+
+    pub compute_unit_limit: u32,
+
+    /// Set to 0 to omit the SetComputeUnitPrice instruction
+    pub compute_unit_price_micro_lamports: u64,
+
+    /// The [`srlut`] lookup table. Required since PrefundSwapViaStake txs
+    /// dont fit without it.
+    pub srlut: &'a AddressLookupTableAccount,
+
+    /// Additional lookup tables to compile the message with
+    pub extra_luts: &'a [AddressLookupTableAccount],
+
+    /// Instructions to run after the compute budget instructions and before the swap,
+    /// e.g. creating the destination token account
+    pub setup_ixs: &'a [Instruction],
+}
+
+impl<'a> SwapTxParams<'a> {
+    /// Defaults to [`SWAP_VIA_STAKE_COMPUTE_BUDGET_LIMIT`], no priority fee,
+    /// no extra lookup tables and no setup instructions
+    pub fn new(
+        payer: Pubkey,
+        recent_blockhash: Hash,
+        srlut: &'a AddressLookupTableAccount,
+    ) -> Self {
+        Self {
+            payer,
+            recent_blockhash,
+            compute_unit_limit: SWAP_VIA_STAKE_COMPUTE_BUDGET_LIMIT,
+            compute_unit_price_micro_lamports: 0,
+            srlut,
+            extra_luts: &[],
+            setup_ixs: &[],
+        }
+    }
+
+    fn compute_budget_ixs(&self) -> Vec<Instruction> {
+        let mut res = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            self.compute_unit_limit,
+        )];
+        if self.compute_unit_price_micro_lamports > 0 {
+            res.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.compute_unit_price_micro_lamports,
+            ));
+        }
+        res
+    }
+}
+
+/// Deserializes the [`srlut`] lookup table from its fetched account data
+pub fn srlut_from_account_data(data: &[u8]) -> Result<AddressLookupTableAccount, StakedexSdkError> {
+    lookup_table_from_account_data(srlut::ID, data)
+}
+
+/// Deserializes an address lookup table from its fetched account data
+pub fn lookup_table_from_account_data(
+    key: Pubkey,
+    data: &[u8],
+) -> Result<AddressLookupTableAccount, StakedexSdkError> {
+    let AddressLookupTable { addresses, .. } = AddressLookupTable::deserialize(data)
+        .map_err(|e| anyhow!("invalid lookup table {}: {}", key, e))?;
+    Ok(AddressLookupTableAccount {
+        key,
+        addresses: addresses.into(),
+    })
+}
+
+/// Compiles `swap_ixs` into a v0 message, prefixed with the compute budget and setup instructions.
+pub fn compile_swap_message(
+    swap_ixs: &[Instruction],
+    tx_params: &SwapTxParams,
+) -> Result<VersionedMessage, StakedexSdkError> {
+    let mut ixs = tx_params.compute_budget_ixs();
+    ixs.extend_from_slice(tx_params.setup_ixs);
+    ixs.extend_from_slice(swap_ixs);
+    let luts: Vec<AddressLookupTableAccount> = std::iter::once(tx_params.srlut)
+        .chain(tx_params.extra_luts)
+        .cloned()
+        .collect();
+    let message = Message::try_compile(&tx_params.payer, &ixs, &luts, tx_params.recent_blockhash)
+        .map_err(|e| StakedexSdkError::Other(e.into()))?;
+    Ok(VersionedMessage::V0(message))
+}
+
+/// The pubkeys that must sign a compiled message, in the order that
+/// their signatures must be placed in the transaction's `signatures`.
+///
+/// The payer is always first, followed by `token_transfer_authority` if it differs from the payer.
+pub fn required_signers(message: &VersionedMessage) -> &[Pubkey] {
+    let n = usize::from(message.header().num_required_signatures);
+    &message.static_account_keys()[..n]
+}
+
+impl Stakedex {
+    /// Builds a ready-to-sign message that executes `route`,
+    /// see [`Self::build_ixs()`]
+    pub fn build_route_message(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        tx_params: &SwapTxParams,
+    ) -> Result<VersionedMessage, StakedexSdkError> {
+        let ixs = self.build_ixs(route, swap_params)?;
+        compile_swap_message(&ixs, tx_params)
+    }
+
+    /// Builds a ready-to-sign message that executes `route` with
+    /// PrefundSwapViaStake split into 2 instructions,
+    /// see [`Self::build_manual_concat_ixs()`]
+    pub fn build_manual_concat_route_message(
+        &self,
+        route: &RouteQuote,
+        swap_params: &SwapParams,
+        tx_params: &SwapTxParams,
+    ) -> Result<VersionedMessage, StakedexSdkError> {
+        let ixs = self.build_manual_concat_ixs(route, swap_params)?;
+        compile_swap_message(&ixs, tx_params)
+    }
+}
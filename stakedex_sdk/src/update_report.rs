@@ -0,0 +1,276 @@
+use std::{collections::HashSet, iter::zip};
+
+use jupiter_amm_interface::AccountMap;
+use solana_sdk::{clock::Clock, pubkey::Pubkey, sysvar};
+use stakedex_sdk_common::{BaseStakePoolAmm, PoolParam, PoolParamValue, PoolReadiness};
+
+use crate::{
+    parallel::{map_mut, SendPool},
+    param_changes::{emit_param_changes, params_before_update},
+    PoolParamChangeListener, Stakedex,
+};
+
+/// Outcome of initializing or updating a single pool
+#[derive(Debug)]
+pub struct PoolUpdateReport {
+    pub main_state_key: Pubkey,
+
+    pub label: String,
+
+    /// Accounts required by the pool that were not in the account map
+    pub missing_accounts: Vec<Pubkey>,
+
+    /// Why the pool failed to initialize or update, if it did.
+    ///
+    /// A pool that fails to update keeps its state from before the failed update
+    pub err: Option<anyhow::Error>,
+
+    /// `None` if the pool failed to initialize and was not added
+    pub readiness: Option<PoolReadiness>,
+
+    /// Whether the pool is excluded from quoting and [`Stakedex::get_amms()`],
+    /// see [`Stakedex::set_quarantine_failing_pools()`]
+    pub quarantined: bool,
+}
+
+impl PoolUpdateReport {
+    fn new(pool: &dyn BaseStakePoolAmm, account_map: &AccountMap) -> Self {
+        Self {
+            main_state_key: pool.main_state_key(),
+            label: pool.stake_pool_label().to_owned(),
+            missing_accounts: missing_accounts(&pool.get_accounts_to_update(), account_map),
+            err: None,
+            readiness: None,
+            quarantined: false,
+        }
+    }
+
+    /// Report for a pool that was added, possibly in its default state if `err` is `Some`
+    pub(crate) fn initialized(
+        pool: &dyn BaseStakePoolAmm,
+        init_account: &Pubkey,
+        accounts: &AccountMap,
+        err: Option<anyhow::Error>,
+    ) -> Self {
+        Self {
+            main_state_key: pool.main_state_key(),
+            label: pool.stake_pool_label().to_owned(),
+            missing_accounts: missing_accounts(&[*init_account], accounts),
+            err,
+            readiness: Some(pool.readiness()),
+            quarantined: false,
+        }
+    }
+
+    /// Report for a pool that failed to initialize and was not added
+    pub(crate) fn init_failed(
+        main_state_key: Pubkey,
+        label: &str,
+        accounts: &AccountMap,
+        err: anyhow::Error,
+    ) -> Self {
+        Self {
+            main_state_key,
+            label: label.to_owned(),
+            missing_accounts: missing_accounts(&[main_state_key], accounts),
+            err: Some(err),
+            readiness: None,
+            quarantined: false,
+        }
+    }
+
+    /// Whether the pool can now be quoted
+    pub fn is_usable(&self) -> bool {
+        self.err.is_none() && !self.quarantined && self.readiness == Some(PoolReadiness::Ready)
+    }
+}
+
+/// Outcome of [`Stakedex::from_fetched_accounts_with_report()`]
+/// or [`Stakedex::update_with_report()`]
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    /// One entry per pool, in the same order as [`Stakedex::all_pools()`]
+    /// followed by any SPL stake pools that failed to initialize
+    pub pools: Vec<PoolUpdateReport>,
+
+    /// Errors not specific to any pool, e.g. failing to deserialize the clock sysvar
+    pub errs: Vec<anyhow::Error>,
+}
+
+impl UpdateReport {
+    pub fn pool(&self, main_state_key: &Pubkey) -> Option<&PoolUpdateReport> {
+        self.pools
+            .iter()
+            .find(|p| p.main_state_key == *main_state_key)
+    }
+
+    pub fn failed_pools(&self) -> impl Iterator<Item = &PoolUpdateReport> {
+        self.pools.iter().filter(|p| p.err.is_some())
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.errs.is_empty() && self.failed_pools().next().is_none()
+    }
+
+    /// Flattens this report into the list of errors returned by
+    /// [`Stakedex::update()`] and [`Stakedex::from_fetched_accounts()`]
+    pub fn into_errs(self) -> Vec<anyhow::Error> {
+        let Self { pools, mut errs } = self;
+        errs.extend(pools.into_iter().filter_map(|p| p.err));
+        errs
+    }
+}
+
+/// The quarantine state and param change listener of [`Stakedex`],
+/// taken out of it while its pools are mutably borrowed
+pub(crate) struct Quarantine {
+    quarantined: HashSet<Pubkey>,
+    quarantine_failing_pools: bool,
+    param_change_listener: Option<PoolParamChangeListener>,
+}
+
+impl Quarantine {
+    pub(crate) fn take(stakedex: &mut Stakedex) -> Self {
+        Self {
+            quarantined: std::mem::take(&mut stakedex.quarantined),
+            quarantine_failing_pools: stakedex.quarantine_failing_pools,
+            param_change_listener: stakedex.param_change_listener.clone(),
+        }
+    }
+
+    pub(crate) fn restore(self, stakedex: &mut Stakedex) {
+        stakedex.quarantined = self.quarantined;
+    }
+
+    /// Updates `pool`, quarantining or releasing it depending on the outcome
+    pub(crate) fn update_pool(
+        &mut self,
+        pool: &mut dyn BaseStakePoolAmm,
+        account_map: &AccountMap,
+    ) -> PoolUpdateReport {
+        let report = PoolUpdateReport::new(&*pool, account_map);
+        let params = params_before_update(self.param_change_listener.as_ref(), &*pool);
+        let res = pool.update(account_map);
+        self.record(report, params, &*pool, res)
+    }
+
+    /// Same as calling [`Self::update_pool()`] on each of `pools` in order,
+    /// but updates them in parallel if the `rayon` feature is enabled
+    pub(crate) fn update_pools(
+        &mut self,
+        mut pools: Vec<SendPool<'_>>,
+        account_map: &AccountMap,
+    ) -> Vec<PoolUpdateReport> {
+        let reports: Vec<PoolUpdateReport> = pools
+            .iter()
+            .map(|p| PoolUpdateReport::new(&**p, account_map))
+            .collect();
+        let params: Vec<_> = pools
+            .iter()
+            .map(|p| params_before_update(self.param_change_listener.as_ref(), &**p))
+            .collect();
+        let results = map_mut(&mut pools, |p| p.update(account_map));
+        zip(zip(reports, params), zip(pools, results))
+            .map(|((report, params), (pool, res))| self.record(report, params, &*pool, res))
+            .collect()
+    }
+
+    /// Also emits the changes of the pool's params since `params_before` to the listener, if any
+    fn record(
+        &mut self,
+        mut report: PoolUpdateReport,
+        params_before: Option<Vec<(PoolParam, PoolParamValue)>>,
+        pool: &dyn BaseStakePoolAmm,
+        res: Result<(), anyhow::Error>,
+    ) -> PoolUpdateReport {
+        emit_param_changes(self.param_change_listener.as_ref(), pool, params_before);
+        match res {
+            Ok(()) => {
+                self.quarantined.remove(&report.main_state_key);
+            }
+            Err(e) => {
+                if self.quarantine_failing_pools {
+                    self.quarantined.insert(report.main_state_key);
+                }
+                report.err = Some(e);
+            }
+        }
+        report.readiness = Some(pool.readiness());
+        report.quarantined = self.quarantined.contains(&report.main_state_key);
+        report
+    }
+}
+
+fn missing_accounts(accounts: &[Pubkey], account_map: &AccountMap) -> Vec<Pubkey> {
+    accounts
+        .iter()
+        .filter(|pk| !account_map.contains_key(*pk))
+        .copied()
+        .collect()
+}
+
+impl Stakedex {
+    /// If enabled, pools that fail to update are excluded from quoting and [`Self::get_amms()`]
+    /// until a subsequent update of theirs succeeds. Disabled by default.
+    ///
+    /// Disabling this does not release pools that are already quarantined.
+    pub fn set_quarantine_failing_pools(&mut self, enabled: bool) {
+        self.quarantine_failing_pools = enabled;
+    }
+
+    pub fn is_quarantined(&self, main_state_key: &Pubkey) -> bool {
+        self.quarantined.contains(main_state_key)
+    }
+
+    /// main_state_keys of all currently quarantined pools
+    pub fn quarantined_pools(&self) -> impl Iterator<Item = &Pubkey> {
+        self.quarantined.iter()
+    }
+
+    /// Same as [`Self::update()`], but reports the outcome of each pool's update
+    ///
+    /// Pools are updated in parallel if the `rayon` feature is enabled
+    pub fn update_with_report(&mut self, account_map: &AccountMap) -> UpdateReport {
+        let mut errs = Vec::new();
+        if let Err(e) = self.update_clock_from_accounts(account_map) {
+            errs.push(e);
+        }
+        let mut quarantine = Quarantine::take(self);
+        // update all pools even if some pools fail to update
+        let pools = quarantine.update_pools(self.all_pools_mut_send(), account_map);
+        quarantine.restore(self);
+        // stake pool account could've been reinitialized with a different mint
+        self.rebuild_spl_indices();
+        self.cache_accounts(account_map);
+        UpdateReport { pools, errs }
+    }
+
+    /// Updates the current epoch from the clock sysvar if it's in `account_map`,
+    /// returning whether the epoch changed
+    pub(crate) fn update_clock_from_accounts(
+        &mut self,
+        account_map: &AccountMap,
+    ) -> Result<bool, anyhow::Error> {
+        let clock_acc = match account_map.get(&sysvar::clock::ID) {
+            Some(acc) => acc,
+            None => return Ok(false),
+        };
+        let clock: Clock = bincode::deserialize(&clock_acc.data)?;
+        let epoch_changed = clock.epoch != self.curr_epoch();
+        self.update_clock(&clock);
+        Ok(epoch_changed)
+    }
+
+    /// Returns `pool` if it is neither quarantined nor excluded by [`crate::StakedexBuilder`]
+    pub(crate) fn unless_quarantined<'a, P: BaseStakePoolAmm + ?Sized>(
+        &self,
+        pool: &'a P,
+    ) -> Option<&'a P> {
+        let main_state_key = pool.main_state_key();
+        if self.is_quarantined(&main_state_key) || self.is_excluded(&main_state_key) {
+            None
+        } else {
+            Some(pool)
+        }
+    }
+}
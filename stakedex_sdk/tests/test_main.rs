@@ -1,5 +1,5 @@
 use jupiter_amm_interface::{
-    AccountMap, AmmContext, ClockRef, Quote, QuoteParams, SwapMode, SwapParams,
+    AccountMap, AmmContext, ClockRef, KeyedAccount, Quote, QuoteParams, SwapMode, SwapParams,
 };
 use lazy_static::lazy_static;
 use sanctum_lst_list::SanctumLstList;
@@ -9,22 +9,26 @@ use solana_client::{
     rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
 };
 use solana_sdk::{
-    account::Account,
-    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
-    clock::Clock,
-    compute_budget,
-    instruction::Instruction,
-    message::{v0::Message, VersionedMessage},
-    program_pack::Pack,
-    pubkey::Pubkey,
-    sysvar,
-    transaction::VersionedTransaction,
+    account::Account, address_lookup_table::AddressLookupTableAccount, clock::Clock,
+    instruction::Instruction, message::VersionedMessage, program_pack::Pack, pubkey::Pubkey,
+    signature::Signature, sysvar, transaction::VersionedTransaction,
 };
 use spl_associated_token_account::get_associated_token_address;
 use spl_token::native_mint;
-use stakedex_sdk::{srlut, Stakedex, SWAP_VIA_STAKE_COMPUTE_BUDGET_LIMIT};
-use stakedex_sdk_common::{bsol, jitosol, jsol, msol, pwrsol};
-use std::{cmp, iter::zip};
+use stakedex_sdk::{
+    accounts_to_update_for_amms, required_signers, srlut, srlut_from_account_data,
+    ConcurrentStakedex, PoolReadiness, RouteKind, RouteQuote, Stakedex, StakedexBuilder,
+    SwapTxParams, DEFAULT_SPLIT_PARTS, MAX_IN_SEARCH_LIMIT,
+};
+use stakedex_sdk_common::{
+    bsol, jitosol, jsol, lido_state, marinade_state, msol, pwrsol, StakedexSdkError,
+};
+use std::{
+    cmp,
+    collections::HashSet,
+    iter::zip,
+    sync::{atomic::Ordering, Arc},
+};
 
 // JSOL whale. Last known balances:
 // - SOL: 1 (enough for a new token account)
@@ -63,14 +67,8 @@ lazy_static! {
         stakedex
     };
     // With the change to PrefundSwapViaStake, all TXs now must use a LUT or it wont fit
-    static ref SRLUT: AddressLookupTableAccount = {
-        let srlut_acc_data = RPC.get_account_data(&srlut::ID).unwrap();
-        let AddressLookupTable { addresses, .. } = AddressLookupTable::deserialize(&srlut_acc_data).unwrap();
-        AddressLookupTableAccount {
-            key: srlut::ID,
-            addresses: Vec::from(addresses),
-        }
-    };
+    static ref SRLUT: AddressLookupTableAccount =
+        srlut_from_account_data(&RPC.get_account_data(&srlut::ID).unwrap()).unwrap();
 }
 
 fn fetch_accounts(accounts_pubkeys: &[Pubkey]) -> AccountMap {
@@ -98,6 +96,436 @@ fn test_swap_via_stake_unknown_token() {
     assert!(res.is_err());
 }
 
+#[test]
+fn test_remove_and_add_spl_pool() {
+    let mut stakedex = STAKEDEX.clone();
+    let removed = stakedex.remove_pool(&jitosol::ID).unwrap();
+    assert!(stakedex.get_deposit_sol_pool(&jitosol::ID).is_none());
+    assert!(!stakedex
+        .get_accounts_to_update()
+        .contains(&removed.inner.stake_pool_addr));
+
+    let pool_acc = RPC.get_account(&removed.inner.stake_pool_addr).unwrap();
+    stakedex
+        .add_spl_pool(
+            &KeyedAccount {
+                key: removed.inner.stake_pool_addr,
+                account: pool_acc,
+                params: None,
+            },
+            "jitoSOL",
+        )
+        .unwrap();
+    let update_accounts = fetch_accounts(&stakedex.get_accounts_to_update());
+    stakedex.update(&update_accounts);
+    stakedex
+        .quote_stake_wrapped_sol(&QuoteParams {
+            amount: 1_000_000_000,
+            input_mint: native_mint::ID,
+            output_mint: jitosol::ID,
+            swap_mode: SwapMode::ExactIn,
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_withdraw_then_stake_wrapped_sol_is_candidate() {
+    let best = STAKEDEX
+        .quote_best(&jsol::ID, &bsol::ID, 1_000_000_000, SwapMode::ExactIn)
+        .unwrap();
+    let two_hop = best
+        .candidates
+        .iter()
+        .find(|rq| rq.kind == RouteKind::WithdrawThenStakeWrappedSol)
+        .unwrap();
+    assert!(two_hop.quote.out_amount <= best.best.quote.out_amount);
+}
+
+#[test]
+fn test_withdraw_then_stake_wrapped_sol_jsol_bsol() {
+    test_sim_route(
+        &STAKEDEX,
+        RouteKind::WithdrawThenStakeWrappedSol,
+        TestSwapViaStakeArgs {
+            amount: 1_000_000_000,
+            input_mint: jsol::ID,
+            output_mint: bsol::ID,
+            signer: whale::ID,
+            src_token_acc: get_associated_token_address(&whale::ID, &jsol::ID),
+            dst_token_acc: get_associated_token_address(&whale::ID, &bsol::ID),
+        },
+    );
+}
+
+#[test]
+fn test_quote_split_jsol_sol() {
+    let amount = 100 * SMALL_JSOL_SWAP_AMT;
+    let split = STAKEDEX
+        .quote_split(&jsol::ID, &native_mint::ID, amount, DEFAULT_SPLIT_PARTS)
+        .unwrap();
+    assert_eq!(split.in_amount, amount);
+    assert_eq!(
+        split.out_amount,
+        split
+            .legs
+            .iter()
+            .map(|leg| leg.quote.out_amount)
+            .sum::<u64>()
+    );
+    assert!(split.legs.iter().all(|leg| leg.quote.in_amount > 0));
+}
+
+#[test]
+fn test_quote_split_jsol_bsol_legs_have_distinct_voters() {
+    let amount = 100 * SMALL_JSOL_SWAP_AMT;
+    let split = STAKEDEX
+        .quote_split(&jsol::ID, &bsol::ID, amount, DEFAULT_SPLIT_PARTS)
+        .unwrap();
+    assert_eq!(split.in_amount, amount);
+    let voters: Vec<Pubkey> = split.legs.iter().filter_map(|leg| leg.voter()).collect();
+    assert_eq!(voters.iter().collect::<HashSet<_>>().len(), voters.len());
+}
+
+#[test]
+fn test_quote_max_in_jsol_withdraw_wrapped_sol() {
+    let max = STAKEDEX
+        .quote_max_in(RouteKind::WithdrawWrappedSol, &jsol::ID, &native_mint::ID)
+        .unwrap();
+    assert!(max.quote.out_amount > 0);
+    let above_max = STAKEDEX.quote_route(
+        RouteKind::WithdrawWrappedSol,
+        &QuoteParams {
+            amount: max.quote.in_amount + 1,
+            input_mint: jsol::ID,
+            output_mint: native_mint::ID,
+            swap_mode: SwapMode::ExactIn,
+        },
+    );
+    assert!(above_max.map_or(true, |rq| rq.quote.out_amount == 0));
+}
+
+#[test]
+fn test_quote_max_in_stake_wrapped_sol_msol_bounded_by_staking_cap() {
+    let max = STAKEDEX
+        .quote_max_in(RouteKind::StakeWrappedSol, &native_mint::ID, &msol::ID)
+        .unwrap();
+    assert!(max.quote.out_amount > 0);
+    assert!(max.quote.in_amount <= STAKEDEX.marinade.state.staking_sol_cap);
+    if max.quote.in_amount < MAX_IN_SEARCH_LIMIT {
+        let above_max = STAKEDEX.quote_route(
+            RouteKind::StakeWrappedSol,
+            &QuoteParams {
+                amount: max.quote.in_amount + 1,
+                input_mint: native_mint::ID,
+                output_mint: msol::ID,
+                swap_mode: SwapMode::ExactIn,
+            },
+        );
+        assert!(matches!(
+            above_max,
+            Err(StakedexSdkError::DepositCapExceeded)
+        ));
+    }
+}
+
+#[test]
+fn test_clock_tracked_and_pools_ready() {
+    assert!(STAKEDEX
+        .get_accounts_to_update()
+        .contains(&sysvar::clock::ID));
+    assert_eq!(STAKEDEX.curr_epoch(), get_clock().epoch);
+    for (pool, readiness) in STAKEDEX.pool_readiness() {
+        assert_ne!(
+            readiness,
+            PoolReadiness::MissingAuxAccounts,
+            "{}",
+            pool.stake_pool_label()
+        );
+    }
+    assert!(STAKEDEX.pool_readiness_of_mint(&jsol::ID).is_some());
+}
+
+#[test]
+fn test_update_report_quarantines_failing_pool() {
+    let mut stakedex = STAKEDEX.clone();
+    stakedex.set_quarantine_failing_pools(true);
+    let jitosol_pool = stakedex
+        .get_spl_pool_by_mint(&jitosol::ID)
+        .unwrap()
+        .inner
+        .stake_pool_addr;
+
+    let mut accounts = fetch_accounts(&stakedex.get_accounts_to_update());
+    accounts.remove(&jitosol_pool);
+    let report = stakedex.update_with_report(&accounts);
+    let jitosol_report = report.pool(&jitosol_pool).unwrap();
+    assert!(jitosol_report.err.is_some());
+    assert!(jitosol_report.missing_accounts.contains(&jitosol_pool));
+    assert!(jitosol_report.quarantined);
+    assert!(!jitosol_report.is_usable());
+    assert!(stakedex.is_quarantined(&jitosol_pool));
+    assert!(stakedex
+        .route_kinds(&native_mint::ID, &jitosol::ID)
+        .is_empty());
+
+    // released once it updates successfully again
+    let accounts = fetch_accounts(&stakedex.get_accounts_to_update());
+    let report = stakedex.update_with_report(&accounts);
+    assert!(!report.pool(&jitosol_pool).unwrap().quarantined);
+    assert!(!stakedex.is_quarantined(&jitosol_pool));
+    assert!(!stakedex
+        .route_kinds(&native_mint::ID, &jitosol::ID)
+        .is_empty());
+}
+
+#[test]
+fn test_update_accounts_only_updates_affected_pools() {
+    let mut stakedex = STAKEDEX.clone();
+    let jitosol_pool = stakedex
+        .get_spl_pool_by_mint(&jitosol::ID)
+        .unwrap()
+        .inner
+        .stake_pool_addr;
+    let changed = fetch_accounts(&[jitosol_pool]);
+    let update = stakedex.update_accounts(&changed);
+    assert!(update.report.is_ok());
+    assert_eq!(
+        update.changed_pools().collect::<Vec<_>>(),
+        vec![&jitosol_pool]
+    );
+    assert!(!update.all_changed);
+    assert!(update.is_mint_pair_changed(&native_mint::ID, &jitosol::ID));
+    assert!(!update.is_mint_pair_changed(&native_mint::ID, &msol::ID));
+}
+
+#[test]
+fn test_concurrent_stakedex_marinade_update_copies_only_marinade() {
+    let concurrent = ConcurrentStakedex::new(STAKEDEX.clone());
+    let before = concurrent.load();
+    concurrent.update_accounts(&fetch_accounts(&[marinade_state::ID]));
+    let after = concurrent.load();
+    assert!(!Arc::ptr_eq(&before.marinade, &after.marinade));
+    assert!(Arc::ptr_eq(&before.unstakeit, &after.unstakeit));
+    assert!(Arc::ptr_eq(&before.lido, &after.lido));
+    for (b, a) in zip(before.spls(), after.spls()) {
+        assert!(Arc::ptr_eq(b, a), "{}", b.inner.stake_pool_label);
+    }
+}
+
+#[test]
+fn test_concurrent_stakedex_copies_only_updated_pools() {
+    let concurrent = ConcurrentStakedex::new(STAKEDEX.clone());
+    let before = concurrent.load();
+    let jitosol_pool = before
+        .get_spl_pool_by_mint(&jitosol::ID)
+        .unwrap()
+        .inner
+        .stake_pool_addr;
+    concurrent.update_accounts(&fetch_accounts(&[jitosol_pool]));
+    let after = concurrent.load();
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert!(Arc::ptr_eq(&before.marinade, &after.marinade));
+    assert!(Arc::ptr_eq(&before.lido, &after.lido));
+    for (b, a) in zip(before.spls(), after.spls()) {
+        assert_eq!(
+            Arc::ptr_eq(b, a),
+            b.inner.stake_pool_addr != jitosol_pool,
+            "{}",
+            b.inner.stake_pool_label
+        );
+    }
+}
+
+#[test]
+fn test_concurrent_epoch_change_does_not_modify_published_snapshot() {
+    let concurrent = ConcurrentStakedex::new(STAKEDEX.clone());
+    let before = concurrent.load();
+    let epoch = before.curr_epoch();
+    concurrent.modify(|stakedex| {
+        stakedex.update_clock(&Clock {
+            epoch: epoch + 1,
+            ..get_clock()
+        })
+    });
+    let after = concurrent.load();
+    assert_eq!(before.curr_epoch(), epoch);
+    assert_eq!(after.curr_epoch(), epoch + 1);
+    for (b, a) in zip(before.spls(), after.spls()) {
+        assert_eq!(b.inner.curr_epoch.load(Ordering::Relaxed), epoch);
+        assert_eq!(a.inner.curr_epoch.load(Ordering::Relaxed), epoch + 1);
+    }
+}
+
+#[test]
+fn test_amm_for_pair_updates_with_minimal_accounts() {
+    let mut amm = STAKEDEX.amm_for_pair(&jsol::ID, &msol::ID).unwrap();
+    let accounts = accounts_to_update_for_amms(std::slice::from_ref(&amm));
+    assert!(accounts.len() < STAKEDEX.get_accounts_to_update().len());
+    amm.update(&fetch_accounts(&accounts)).unwrap();
+    let quote = amm
+        .quote(&QuoteParams {
+            amount: 1_000_000_000,
+            input_mint: jsol::ID,
+            output_mint: msol::ID,
+            swap_mode: SwapMode::ExactIn,
+        })
+        .unwrap();
+    assert!(quote.out_amount > 0);
+
+    let amms = STAKEDEX.amms_for_mints(&[jitosol::ID, bsol::ID, native_mint::ID]);
+    // 2 DepositWithdrawSol + jitoSOL <-> bSOL + 2 (Prefund)SwapViaStake to SOL via unstake.it
+    assert_eq!(amms.len(), 5);
+}
+
+#[test]
+fn test_builder_only_loads_included_pools() {
+    let builder = StakedexBuilder::new()
+        .with_spl_mints([jitosol::ID, bsol::ID])
+        .with_marinade(false)
+        .with_lido(false);
+    let init_accounts = builder.init_accounts();
+    assert_eq!(init_accounts.len(), 3);
+    assert!(!init_accounts.contains(&marinade_state::ID));
+    assert!(!init_accounts.contains(&lido_state::ID));
+
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::from(get_clock()),
+    };
+    let (mut stakedex, errs) = builder.build(&fetch_accounts(&init_accounts), &amm_context);
+    assert!(errs.is_empty(), "{:?}", errs);
+    // only the included pools' accounts are required
+    let update_accounts = stakedex.get_accounts_to_update();
+    assert!(update_accounts.len() < STAKEDEX.get_accounts_to_update().len());
+    stakedex.update(&fetch_accounts(&update_accounts));
+    assert_eq!(stakedex.spls().len(), 2);
+    assert!(stakedex.is_excluded(&marinade_state::ID));
+    assert!(stakedex.get_deposit_stake_pool(&msol::ID).is_none());
+
+    let quote = stakedex
+        .quote_best(&jitosol::ID, &bsol::ID, 1_000_000_000, SwapMode::ExactIn)
+        .unwrap();
+    assert!(quote.best.quote.out_amount > 0);
+    assert_eq!(stakedex.get_amms().len(), 5);
+
+    // exclusions survive a snapshot round trip
+    let bytes_restored =
+        Stakedex::from_snapshot_bytes(&stakedex.to_snapshot_bytes().unwrap()).unwrap();
+    let json_restored =
+        Stakedex::from_snapshot_json(&stakedex.to_snapshot_json().unwrap()).unwrap();
+    for restored in [bytes_restored, json_restored] {
+        assert!(restored.is_excluded(&marinade_state::ID));
+        assert!(restored.is_excluded(&lido_state::ID));
+        assert_eq!(restored.get_amms().len(), 5);
+    }
+}
+
+#[test]
+fn test_builder_spl_lsts_dedups_mints() {
+    let sanctum_lst_list = SanctumLstList::load();
+    let jitosol_lst = sanctum_lst_list
+        .sanctum_lst_list
+        .iter()
+        .find(|lst| lst.mint == jitosol::ID)
+        .unwrap();
+    let builder = StakedexBuilder::new()
+        .with_spl_mints([jitosol::ID])
+        .with_sanctum_lsts([jitosol_lst, jitosol_lst]);
+    assert_eq!(builder.spl_lsts().count(), 1);
+    assert_eq!(
+        builder.init_accounts(),
+        StakedexBuilder::new()
+            .with_spl_mints([jitosol::ID])
+            .init_accounts()
+    );
+}
+
+#[test]
+fn test_quote_best_batch_matches_quote_best() {
+    let quote_params: Vec<QuoteParams> = [
+        (native_mint::ID, jitosol::ID),
+        (bsol::ID, native_mint::ID),
+        (jsol::ID, msol::ID),
+        (msol::ID, jsol::ID),
+    ]
+    .into_iter()
+    .map(|(input_mint, output_mint)| QuoteParams {
+        amount: 1_000_000_000,
+        input_mint,
+        output_mint,
+        swap_mode: SwapMode::ExactIn,
+    })
+    .collect();
+    let batch = STAKEDEX.quote_best_batch(&quote_params);
+    assert_eq!(batch.len(), quote_params.len());
+    for (qp, res) in zip(&quote_params, batch) {
+        let expected =
+            STAKEDEX.quote_best(&qp.input_mint, &qp.output_mint, qp.amount, qp.swap_mode);
+        match (expected, res) {
+            (Ok(e), Ok(r)) => assert_eq!(e.best.quote.out_amount, r.best.quote.out_amount),
+            (Err(e), Err(r)) => assert_eq!(e.to_string(), r.to_string()),
+            (e, r) => panic!("{:?} != {:?}", e.map(|b| b.best), r.map(|b| b.best)),
+        }
+    }
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let bytes_restored =
+        Stakedex::from_snapshot_bytes(&STAKEDEX.to_snapshot_bytes().unwrap()).unwrap();
+    let json_restored =
+        Stakedex::from_snapshot_json(&STAKEDEX.to_snapshot_json().unwrap()).unwrap();
+    let routes = [
+        (native_mint::ID, jitosol::ID),
+        (native_mint::ID, msol::ID),
+        (bsol::ID, native_mint::ID),
+        (jsol::ID, native_mint::ID),
+        (jsol::ID, msol::ID),
+    ];
+    for restored in [bytes_restored, json_restored] {
+        assert_eq!(restored.curr_epoch(), STAKEDEX.curr_epoch());
+        for (input_mint, output_mint) in routes {
+            let expected = STAKEDEX
+                .quote_best(&input_mint, &output_mint, 1_000_000_000, SwapMode::ExactIn)
+                .unwrap();
+            let actual = restored
+                .quote_best(&input_mint, &output_mint, 1_000_000_000, SwapMode::ExactIn)
+                .unwrap();
+            assert_eq!(actual.best.kind, expected.best.kind);
+            assert_eq!(actual.best.quote.in_amount, expected.best.quote.in_amount);
+            assert_eq!(actual.best.quote.out_amount, expected.best.quote.out_amount);
+            assert_eq!(actual.best.quote.fee_amount, expected.best.quote.fee_amount);
+        }
+    }
+}
+
+#[test]
+fn test_swap_via_stake_exact_out_jsol_unstakeit() {
+    let out_amount = 1_000_000_000;
+    let exact_out = STAKEDEX
+        .quote_swap_via_stake(&QuoteParams {
+            amount: out_amount,
+            input_mint: jsol::ID,
+            output_mint: native_mint::ID,
+            swap_mode: SwapMode::ExactOut,
+        })
+        .unwrap();
+    assert!(exact_out.out_amount >= out_amount);
+    let exact_in = |amount| {
+        STAKEDEX.quote_swap_via_stake(&QuoteParams {
+            amount,
+            input_mint: jsol::ID,
+            output_mint: native_mint::ID,
+            swap_mode: SwapMode::ExactIn,
+        })
+    };
+    assert_eq!(
+        exact_in(exact_out.in_amount).unwrap().out_amount,
+        exact_out.out_amount
+    );
+    // in_amount should be the minimum
+    assert!(exact_in(exact_out.in_amount - 1).map_or(true, |q| q.out_amount < out_amount));
+}
+
 const SMALL_JSOL_SWAP_AMT: u64 = 10_000_000_000; // 10 JSOL
 
 // unstakeit
@@ -318,7 +746,6 @@ fn test_jsol_drain_vsa_edge_case() {
     let largest_active_stake_vsi = STAKEDEX
         .jpool
         .validator_list
-        .validators
         .iter()
         .max_by_key(|v| v.active_stake_lamports)
         .unwrap();
@@ -382,7 +809,7 @@ pub struct TestSwapViaStakeArgs {
 /// - if dst_token_acc is signer's ATA and doesn't exist, prefixes
 ///   the simulated tx with a create ATA instruction
 ///
-/// Returns (amt_to_swap, prefix_ixs, before_source_amount, before_destination_amount)
+/// Returns (amt_to_swap, setup_ixs, before_source_amount, before_destination_amount)
 fn setup_swap_via_stake(
     TestSwapViaStakeArgs {
         amount,
@@ -400,23 +827,17 @@ fn setup_swap_via_stake(
         })
         .unwrap();
 
-    let mut ixs = Vec::with_capacity(4);
-    ixs.extend([
-        compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(
-            SWAP_VIA_STAKE_COMPUTE_BUDGET_LIMIT,
-        ),
-        compute_budget::ComputeBudgetInstruction::set_compute_unit_price(3),
-    ]);
-
-    let (before_destination_amount, prefix_ixs) =
-        match RPC.get_token_account_balance(&dst_token_acc) {
-            Ok(b) => (b.amount.parse().unwrap(), ixs),
-            Err(_e) => {
-                let ata = get_associated_token_address(&signer, &output_mint);
-                if dst_token_acc != ata {
-                    panic!("dst_token_acc {dst_token_acc} does not exist and is not ATA");
-                }
-                ixs.push(
+    let (before_destination_amount, setup_ixs) = match RPC.get_token_account_balance(&dst_token_acc)
+    {
+        Ok(b) => (b.amount.parse().unwrap(), vec![]),
+        Err(_e) => {
+            let ata = get_associated_token_address(&signer, &output_mint);
+            if dst_token_acc != ata {
+                panic!("dst_token_acc {dst_token_acc} does not exist and is not ATA");
+            }
+            (
+                0,
+                vec![
                     spl_associated_token_account::instruction::create_associated_token_account(
                         &signer,
                         &signer,
@@ -424,37 +845,41 @@ fn setup_swap_via_stake(
                         // TODO: support token-22
                         &spl_token::ID,
                     ),
-                );
-                (0, ixs)
-            }
-        };
+                ],
+            )
+        }
+    };
     let before_source_amount: u64 = source_balance.amount.parse().unwrap();
     let amount = cmp::min(before_source_amount, amount);
 
     (
         amount,
-        prefix_ixs,
+        setup_ixs,
         before_source_amount,
         before_destination_amount,
     )
 }
 
-fn quote_swap_via_stake(
+fn quote_route(
     stakedex: &Stakedex,
+    kind: RouteKind,
     amount: u64,
     TestSwapViaStakeArgs {
         input_mint,
         output_mint,
         ..
     }: TestSwapViaStakeArgs,
-) -> Quote {
-    match stakedex.quote_swap_via_stake(&QuoteParams {
-        amount,
-        input_mint,
-        output_mint,
-        swap_mode: SwapMode::default(),
-    }) {
-        Ok(q) => q,
+) -> RouteQuote {
+    match stakedex.quote_route(
+        kind,
+        &QuoteParams {
+            amount,
+            input_mint,
+            output_mint,
+            swap_mode: SwapMode::default(),
+        },
+    ) {
+        Ok(rq) => rq,
         Err(err) => {
             panic!(
                 "Could not swap {} {} to {}. Reason: {}",
@@ -488,16 +913,14 @@ fn simulate_check_swap_via_stake(
         dst_token_acc,
     }: TestSwapViaStakeArgs,
     quote: Quote,
-    ixs: Vec<Instruction>,
+    message: VersionedMessage,
     before_source_amount: u64,
     before_destination_amount: u64,
 ) {
-    let rbh = RPC.get_latest_blockhash().unwrap();
+    assert_eq!(required_signers(&message), [signer]);
     let tx = VersionedTransaction {
-        signatures: vec![Default::default()], // for payer
-        message: VersionedMessage::V0(
-            Message::try_compile(&signer, &ixs, &[SRLUT.to_owned()], rbh).unwrap(),
-        ),
+        signatures: vec![Signature::default()], // for payer
+        message,
     };
 
     let result = RPC
@@ -548,6 +971,10 @@ fn simulate_check_swap_via_stake(
 }
 
 pub fn test_sim_prefund_swap_via_stake(stakedex: &Stakedex, args: TestSwapViaStakeArgs) {
+    test_sim_route(stakedex, RouteKind::PrefundSwapViaStake, args);
+}
+
+pub fn test_sim_route(stakedex: &Stakedex, kind: RouteKind, args: TestSwapViaStakeArgs) {
     let TestSwapViaStakeArgs {
         input_mint,
         output_mint,
@@ -557,37 +984,40 @@ pub fn test_sim_prefund_swap_via_stake(stakedex: &Stakedex, args: TestSwapViaSta
         ..
     } = args;
 
-    let (amount, mut ixs, before_source_amount, before_destination_amount) =
+    let (amount, setup_ixs, before_source_amount, before_destination_amount) =
         setup_swap_via_stake(args);
 
-    let quote = quote_swap_via_stake(stakedex, amount, args);
+    let route = quote_route(stakedex, kind, amount, args);
 
-    ixs.push(
-        stakedex
-            .prefund_swap_via_stake_ix(
-                &SwapParams {
-                    jupiter_program_id: &jupiter_program::ID,
-                    in_amount: quote.in_amount,
-                    out_amount: quote.out_amount,
-                    destination_mint: output_mint,
-                    source_mint: input_mint,
-                    destination_token_account: dst_token_acc,
-                    source_token_account: src_token_acc,
-                    token_transfer_authority: signer,
-                    open_order_address: None,
-                    quote_mint_to_referrer: None,
-                    missing_dynamic_accounts_as_default: false,
-                    swap_mode: SwapMode::ExactIn,
-                },
-                0,
-            )
-            .unwrap(),
-    );
+    let message = stakedex
+        .build_route_message(
+            &route,
+            &SwapParams {
+                jupiter_program_id: &jupiter_program::ID,
+                in_amount: route.quote.in_amount,
+                out_amount: route.quote.out_amount,
+                destination_mint: output_mint,
+                source_mint: input_mint,
+                destination_token_account: dst_token_acc,
+                source_token_account: src_token_acc,
+                token_transfer_authority: signer,
+                open_order_address: None,
+                quote_mint_to_referrer: None,
+                missing_dynamic_accounts_as_default: false,
+                swap_mode: SwapMode::ExactIn,
+            },
+            &SwapTxParams {
+                compute_unit_price_micro_lamports: 3,
+                setup_ixs: &setup_ixs,
+                ..SwapTxParams::new(signer, RPC.get_latest_blockhash().unwrap(), &SRLUT)
+            },
+        )
+        .unwrap();
 
     simulate_check_swap_via_stake(
         args,
-        quote,
-        ixs,
+        route.quote,
+        message,
         before_source_amount,
         before_destination_amount,
     );
@@ -606,37 +1036,40 @@ pub fn test_sim_manual_concat_prefund_swap_via_stake(
         ..
     } = args;
 
-    let (amount, mut ixs, before_source_amount, before_destination_amount) =
+    let (amount, setup_ixs, before_source_amount, before_destination_amount) =
         setup_swap_via_stake(args);
 
-    let quote = quote_swap_via_stake(stakedex, amount, args);
+    let route = quote_route(stakedex, RouteKind::PrefundSwapViaStake, amount, args);
 
-    ixs.extend(
-        stakedex
-            .manual_concat_prefund_swap_via_stake_ixs(
-                &SwapParams {
-                    jupiter_program_id: &jupiter_program::ID,
-                    in_amount: quote.in_amount,
-                    out_amount: quote.out_amount,
-                    destination_mint: output_mint,
-                    source_mint: input_mint,
-                    destination_token_account: dst_token_acc,
-                    source_token_account: src_token_acc,
-                    token_transfer_authority: signer,
-                    open_order_address: None,
-                    quote_mint_to_referrer: None,
-                    missing_dynamic_accounts_as_default: false,
-                    swap_mode: SwapMode::ExactIn,
-                },
-                0,
-            )
-            .unwrap(),
-    );
+    let message = stakedex
+        .build_manual_concat_route_message(
+            &route,
+            &SwapParams {
+                jupiter_program_id: &jupiter_program::ID,
+                in_amount: route.quote.in_amount,
+                out_amount: route.quote.out_amount,
+                destination_mint: output_mint,
+                source_mint: input_mint,
+                destination_token_account: dst_token_acc,
+                source_token_account: src_token_acc,
+                token_transfer_authority: signer,
+                open_order_address: None,
+                quote_mint_to_referrer: None,
+                missing_dynamic_accounts_as_default: false,
+                swap_mode: SwapMode::ExactIn,
+            },
+            &SwapTxParams {
+                compute_unit_price_micro_lamports: 3,
+                setup_ixs: &setup_ixs,
+                ..SwapTxParams::new(signer, RPC.get_latest_blockhash().unwrap(), &SRLUT)
+            },
+        )
+        .unwrap();
 
     simulate_check_swap_via_stake(
         args,
-        quote,
-        ixs,
+        route.quote,
+        message,
         before_source_amount,
         before_destination_amount,
     );